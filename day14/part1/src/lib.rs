@@ -0,0 +1,202 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::io::BufRead;
+use std::ops::Bound::{Excluded, Unbounded};
+
+#[derive(Debug)]
+enum Tile {
+    Rock,
+    Sand,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum Floor {
+    // sand falling below the lowest rock is lost
+    Abyss,
+    // infinite floor at the given y
+    Depth(i32),
+    // infinite floor at the given distance below the lowest rock
+    BelowLowest(i32),
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum Stop {
+    // stop as soon as one grain falls into the abyss
+    Abyss,
+    // stop once every source is covered by sand
+    SourceBlocked,
+}
+
+// where sand comes from, what bounds the cave and when the simulation ends
+pub struct Config<'a> {
+    pub sources: &'a [(i32, i32)],
+    pub floor: Floor,
+    pub walls: Option<(i32, i32)>,
+    pub stop: Stop,
+}
+
+struct Cave {
+    map: HashMap<i32, BTreeMap<i32, Tile>>,
+    floor: Option<i32>,
+    walls: Option<(i32, i32)>,
+}
+
+impl Cave {
+    fn is_free(&self, (x, y): (i32, i32)) -> bool {
+        if let Some((left, right)) = self.walls {
+            if x <= left || x >= right {
+                return false;
+            }
+        }
+        if let Some(floor) = self.floor {
+            if y >= floor {
+                return false;
+            }
+        }
+        self.map.get(&x).and_then(|col| col.get(&y)).is_none()
+    }
+
+    // first occupied y below the given position, None if it's the abyss
+    fn below(&self, (x, y): (i32, i32)) -> Option<i32> {
+        let tile = self
+            .map
+            .get(&x)
+            .and_then(|col| col.range((Excluded(y), Unbounded)).next())
+            .map(|(y, _)| *y);
+        match (tile, self.floor) {
+            (Some(tile), Some(floor)) => Some(tile.min(floor)),
+            (tile, floor) => tile.or(floor),
+        }
+    }
+
+    // returns where the grain came to rest, None if it fell into the abyss
+    fn drop_sand(&mut self, mut sand: (i32, i32)) -> Option<(i32, i32)> {
+        'main: loop {
+            let y = self.below(sand)?;
+            for dx in [-1, 1] {
+                if self.is_free((sand.0 + dx, y)) {
+                    sand.0 += dx;
+                    sand.1 = y;
+                    continue 'main;
+                };
+            }
+            sand.1 = y - 1;
+            break;
+        }
+
+        self.map
+            .entry(sand.0)
+            .or_default()
+            .insert(sand.1, Tile::Sand);
+        Some(sand)
+    }
+}
+
+// number of grains that came to rest before the stop condition, from the rock paths of the input
+pub fn simulate(input: impl BufRead, config: &Config) -> Result<usize, Box<dyn Error>> {
+    let mut lines = input.lines().map_while(Result::ok);
+    let mut max = None;
+
+    let map = lines.try_fold(
+        HashMap::<i32, BTreeMap<i32, Tile>>::new(),
+        |mut map, line| {
+            let mut positions = line.split(" -> ").map(|xy| {
+                match xy.split_once(',').map(|parts| {
+                    Ok::<(i32, i32), Box<dyn Error>>((parts.0.parse()?, parts.1.parse()?))
+                }) {
+                    Some(Ok((x, y))) => Ok((x, y)),
+                    Some(Err(e)) => Err(e),
+                    None => Err("invalid coordinates".into()),
+                }
+            });
+
+            let mut source: (i32, i32) = positions
+                .next()
+                .ok_or("expected at least two positions")??;
+
+            for destination in positions {
+                let destination: (i32, i32) = destination?;
+                let dx = (destination.0 - source.0).signum();
+                let dy = (destination.1 - source.1).signum();
+                if dx != 0 && dy != 0 {
+                    return Err("diagonal moves not allowed".into());
+                }
+                loop {
+                    let col = map.entry(source.0).or_default();
+                    col.insert(source.1, Tile::Rock);
+
+                    max = Some(max.map_or(source.1, |max: i32| max.max(source.1)));
+
+                    if source == destination {
+                        break;
+                    }
+                    source = (source.0 + dx, source.1 + dy);
+                }
+            }
+
+            Ok::<_, Box<dyn Error>>(map)
+        },
+    )?;
+
+    let floor = match config.floor {
+        Floor::Abyss => None,
+        Floor::Depth(depth) => Some(depth),
+        Floor::BelowLowest(distance) => Some(max.ok_or("no lowest point found")? + distance),
+    };
+
+    if let Some((left, right)) = config.walls {
+        if config
+            .sources
+            .iter()
+            .any(|(x, _)| *x <= left || *x >= right)
+        {
+            return Err("sources must be between the walls".into());
+        }
+    }
+
+    let mut cave = Cave {
+        map,
+        floor,
+        walls: config.walls,
+    };
+
+    let mut blocked = vec![false; config.sources.len()];
+    let mut result = 0;
+
+    'simulation: loop {
+        let mut settled = false;
+
+        for (&source, blocked) in config.sources.iter().zip(blocked.iter_mut()) {
+            if *blocked {
+                continue;
+            }
+            if !cave.is_free(source) {
+                *blocked = true;
+                continue;
+            }
+            match cave.drop_sand(source) {
+                Some(sand) => {
+                    result += 1;
+                    settled = true;
+                    if sand == source {
+                        *blocked = true;
+                    }
+                }
+                None => {
+                    if let Stop::Abyss = config.stop {
+                        break 'simulation;
+                    }
+                }
+            }
+        }
+
+        if blocked.iter().all(|blocked| *blocked) {
+            break;
+        }
+        if !settled {
+            return Err("sand keeps falling into the abyss".into());
+        }
+    }
+
+    Ok(result)
+}
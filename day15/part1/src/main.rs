@@ -1,18 +1,22 @@
-use std::collections::{BTreeSet, HashSet};
-use std::io::{BufRead, stdin};
-use std::ops::Neg;
+use std::collections::HashSet;
+use std::env::args;
+use std::fmt::Write;
+use std::fs;
+use std::io::{stdin, BufRead};
+use std::marker::PhantomData;
+use std::ops::{Neg, RangeInclusive};
 use std::str::FromStr;
 
+use nom::error::VerboseErrorKind;
 use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::digit1,
     combinator::{all_consuming, map_res},
     error::{ContextError, FromExternalError, ParseError, VerboseError},
-    Finish,
-    IResult, sequence::tuple,
+    sequence::tuple,
+    Finish, IResult,
 };
-use nom::error::VerboseErrorKind;
 
 trait Number: FromStr + Neg<Output = Self> {}
 
@@ -33,36 +37,6 @@ fn number<'a, E: ExpressionParseError<'a, T>, T: Number>(i: &'a str) -> IResult<
     ))(i)
 }
 
-trait Intervals {
-    fn add(&mut self, start: i32, end: i32);
-    fn count(&self) -> usize;
-}
-
-impl Intervals for BTreeSet<(i32, i32)> {
-    fn add(&mut self, start: i32, end: i32) {
-        let mut min = start;
-        let mut max = end;
-
-        self.retain(|(start1, end1)| {
-            if *end1 >= start && *start1 <= end {
-                min = min.min(*start1);
-                max = max.max(*end1);
-                false
-            } else {
-                true
-            }
-        });
-        self.insert((min, max));
-    }
-
-    fn count(&self) -> usize {
-        self.iter()
-            .copied()
-            .map(|(start, end)| (end - start + 1) as usize)
-            .sum()
-    }
-}
-
 fn line<'a, T: Number, E: ExpressionParseError<'a, T>>(
     i: &'a str,
 ) -> IResult<&'a str, (T, T, T, T), E> {
@@ -80,11 +54,384 @@ fn line<'a, T: Number, E: ExpressionParseError<'a, T>>(
     Ok((i, (x1, y1, x2, y2)))
 }
 
+trait Metric {
+    fn distance(a: (i32, i32), b: (i32, i32)) -> i32;
+    // half width of the interval covered dy rows away from the center, negative if out of reach
+    fn reach(distance: i32, dy: i32) -> i32;
+    // corners of the area covered around the center, for drawing
+    fn outline(center: (f64, f64), distance: f64) -> [(f64, f64); 4];
+}
+
+#[derive(Debug)]
+struct Manhattan;
+
+impl Metric for Manhattan {
+    fn distance(a: (i32, i32), b: (i32, i32)) -> i32 {
+        (a.0 - b.0).abs() + (a.1 - b.1).abs()
+    }
+
+    fn reach(distance: i32, dy: i32) -> i32 {
+        distance - dy.abs()
+    }
+
+    fn outline((x, y): (f64, f64), d: f64) -> [(f64, f64); 4] {
+        [(x - d, y), (x, y - d), (x + d, y), (x, y + d)]
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+struct Chebyshev;
+
+impl Metric for Chebyshev {
+    fn distance(a: (i32, i32), b: (i32, i32)) -> i32 {
+        (a.0 - b.0).abs().max((a.1 - b.1).abs())
+    }
+
+    fn reach(distance: i32, dy: i32) -> i32 {
+        if dy.abs() <= distance {
+            distance
+        } else {
+            -1
+        }
+    }
+
+    fn outline((x, y): (f64, f64), d: f64) -> [(f64, f64); 4] {
+        [
+            (x - d, y - d),
+            (x + d, y - d),
+            (x + d, y + d),
+            (x - d, y + d),
+        ]
+    }
+}
+
+#[derive(Debug)]
+struct Sensor<M: Metric> {
+    location: (i32, i32),
+    beacon: (i32, i32),
+    distance: i32,
+    metric: PhantomData<M>,
+}
+
+impl<M: Metric> Sensor<M> {
+    fn new(location: (i32, i32), beacon: (i32, i32)) -> Self {
+        Self {
+            location,
+            beacon,
+            distance: M::distance(location, beacon),
+            metric: PhantomData,
+        }
+    }
+
+    // inclusive interval covered on the given row, if any
+    fn row(&self, y: i32) -> Option<(i32, i32)> {
+        let remains = M::reach(self.distance, self.location.1 - y);
+        (remains >= 0).then_some((self.location.0 - remains, self.location.0 + remains))
+    }
+}
+
+struct Coverage<M: Metric> {
+    sensors: Vec<Sensor<M>>,
+    beacons: HashSet<(i32, i32)>,
+}
+
+impl<M: Metric> Coverage<M> {
+    // sorted, disjoint and inclusive intervals covered on the given row
+    fn row(&self, y: i32) -> Vec<(i32, i32)> {
+        let mut intervals = self
+            .sensors
+            .iter()
+            .filter_map(|sensor| sensor.row(y))
+            .collect::<Vec<_>>();
+        intervals.sort_unstable();
+
+        intervals
+            .into_iter()
+            .fold(Vec::new(), |mut merged: Vec<(i32, i32)>, (start, end)| {
+                match merged.last_mut() {
+                    Some((_, last)) if start <= *last + 1 => *last = (*last).max(end),
+                    _ => merged.push((start, end)),
+                }
+                merged
+            })
+    }
+
+    fn rows(&self, rows: RangeInclusive<i32>) -> impl Iterator<Item = (i32, Vec<(i32, i32)>)> + '_ {
+        rows.map(|y| (y, self.row(y)))
+    }
+
+    // known beacons between min and max, inclusive. They're always covered by their sensor
+    fn beacons(&self, min: (i32, i32), max: (i32, i32)) -> usize {
+        self.beacons
+            .iter()
+            .filter(|(x, y)| (min.0..=max.0).contains(x) && (min.1..=max.1).contains(y))
+            .count()
+    }
+
+    // covered positions between min and max, inclusive
+    fn area(&self, min: (i32, i32), max: (i32, i32)) -> usize {
+        self.rows(min.1..=max.1)
+            .map(|(_, intervals)| count(&intervals, min.0, max.0))
+            .sum()
+    }
+}
+
+// positions of the intervals that are between min and max
+fn count(intervals: &[(i32, i32)], min: i32, max: i32) -> usize {
+    intervals
+        .iter()
+        .map(|(start, end)| ((*end).min(max) - (*start).max(min) + 1).max(0) as usize)
+        .sum()
+}
+
+// width and height of the svg picture, in pixels
+const SVG_SIZE: f64 = 1000.;
+const SVG_MARGIN: f64 = 20.;
+
+// svg picture mapping puzzle coordinates between min and max into the picture. Positions are the
+// center of their cell, so shapes covering cells are extended by half a cell
+struct Svg {
+    min: (f64, f64),
+    max: (f64, f64),
+    scale: f64,
+    body: String,
+}
+
+impl Svg {
+    fn new(min: (i32, i32), max: (i32, i32)) -> Self {
+        let extent = (max.0 - min.0).max(max.1 - min.1) as f64 + 1.;
+        let mut svg = Self {
+            min: (min.0 as f64 - 0.5, min.1 as f64 - 0.5),
+            max: (max.0 as f64 + 0.5, max.1 as f64 + 0.5),
+            scale: (SVG_SIZE - 2. * SVG_MARGIN) / extent,
+            body: String::new(),
+        };
+        svg.clip_path("view", svg.min, svg.max);
+        svg
+    }
+
+    // brings a point within a cell around the view, so that shapes reaching far out of it stay
+    // reasonably sized once clipped to `url(#view)`
+    fn clamp(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (
+            x.clamp(self.min.0 - 1., self.max.0 + 1.),
+            y.clamp(self.min.1 - 1., self.max.1 + 1.),
+        )
+    }
+
+    fn contains(&self, (x, y): (f64, f64)) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
+    }
+
+    fn point(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (
+            (x - self.min.0) * self.scale + SVG_MARGIN,
+            (y - self.min.1) * self.scale + SVG_MARGIN,
+        )
+    }
+
+    // cells are drawn at least that many pixels wide
+    fn size(&self, pixels: f64) -> f64 {
+        pixels.max(self.scale)
+    }
+
+    // the convex polygon cut to a cell around the view, one side of that box after the other
+    fn crop(&self, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        let (min, max) = (
+            (self.min.0 - 1., self.min.1 - 1.),
+            (self.max.0 + 1., self.max.1 + 1.),
+        );
+        // how far inside each side a point is
+        let sides: [&dyn Fn((f64, f64)) -> f64; 4] = [
+            &|(x, _)| x - min.0,
+            &|(x, _)| max.0 - x,
+            &|(_, y)| y - min.1,
+            &|(_, y)| max.1 - y,
+        ];
+        sides.iter().fold(points.to_vec(), |points, inside| {
+            let mut cropped = vec![];
+            for (i, &a) in points.iter().enumerate() {
+                let b = points[(i + 1) % points.len()];
+                let (da, db) = (inside(a), inside(b));
+                if da >= 0. {
+                    cropped.push(a);
+                }
+                if (da >= 0.) != (db >= 0.) {
+                    let t = da / (da - db);
+                    cropped.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+                }
+            }
+            cropped
+        })
+    }
+
+    fn polygon(&mut self, points: &[(f64, f64)], style: &str) {
+        let points = self.crop(points);
+        if points.is_empty() {
+            return;
+        }
+        let points = points
+            .iter()
+            .map(|point| {
+                let (x, y) = self.point(*point);
+                format!("{:.2},{:.2}", x, y)
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(self.body, r#"<polygon points="{}" {}/>"#, points, style).unwrap();
+    }
+
+    fn rect(&mut self, min: (f64, f64), max: (f64, f64), style: &str) {
+        let (x0, y0) = self.point(min);
+        let (x1, y1) = self.point(max);
+        writeln!(
+            self.body,
+            r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" {}/>"#,
+            x0,
+            y0,
+            x1 - x0,
+            y1 - y0,
+            style
+        )
+        .unwrap();
+    }
+
+    fn clip_path(&mut self, id: &str, min: (f64, f64), max: (f64, f64)) {
+        writeln!(self.body, r#"<clipPath id="{}">"#, id).unwrap();
+        self.rect(min, max, "");
+        writeln!(self.body, "</clipPath>").unwrap();
+    }
+
+    fn line(&mut self, from: (f64, f64), to: (f64, f64), style: &str) {
+        let (x0, y0) = self.point(from);
+        let (x1, y1) = self.point(to);
+        writeln!(
+            self.body,
+            r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" {}/>"#,
+            x0, y0, x1, y1, style
+        )
+        .unwrap();
+    }
+
+    // marker of a cell, centered on the position
+    fn cell(&mut self, (x, y): (i32, i32), pixels: f64, style: &str) {
+        let (x, y) = self.point((x as f64, y as f64));
+        let size = self.size(pixels);
+        writeln!(
+            self.body,
+            r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" {}/>"#,
+            x - size / 2.,
+            y - size / 2.,
+            size,
+            size,
+            style
+        )
+        .unwrap();
+    }
+
+    fn circle(&mut self, (x, y): (i32, i32), pixels: f64, style: &str) {
+        let (x, y) = self.point((x as f64, y as f64));
+        writeln!(
+            self.body,
+            r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" {}/>"#,
+            x,
+            y,
+            self.size(pixels) / 2.,
+            style
+        )
+        .unwrap();
+    }
+
+    fn finish(self) -> String {
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {size} {size}">
+<rect width="100%" height="100%" fill="white"/>
+{}</svg>
+"#,
+            self.body,
+            size = SVG_SIZE
+        )
+    }
+}
+
+// area covered by the sensor, with the sensor and a line to its closest beacon
+fn draw_sensor<M: Metric>(svg: &mut Svg, sensor: &Sensor<M>) {
+    let (x, y) = (sensor.location.0 as f64, sensor.location.1 as f64);
+    svg.polygon(
+        &M::outline((x, y), sensor.distance as f64 + 0.5),
+        r#"fill="steelblue" fill-opacity="0.15" stroke="steelblue" stroke-width="1" clip-path="url(#view)""#,
+    );
+    svg.line(
+        (x, y),
+        (sensor.beacon.0 as f64, sensor.beacon.1 as f64),
+        r#"stroke="gray" stroke-width="1" stroke-dasharray="4 2""#,
+    );
+    svg.circle(sensor.location, 6., r#"fill="steelblue""#);
+    svg.cell(sensor.beacon, 6., r#"fill="darkorange""#);
+}
+
+// the view fits the sensors and beacons, the row and the area are only drawn where they cross it
+fn svg<M: Metric>(
+    coverage: &Coverage<M>,
+    row: i32,
+    area: Option<((i32, i32), (i32, i32))>,
+) -> String {
+    let (min, max) = coverage
+        .sensors
+        .iter()
+        .flat_map(|sensor| [sensor.location, sensor.beacon])
+        .map(|position| (position, position))
+        .reduce(|(min, max), (low, high)| {
+            (
+                (min.0.min(low.0), min.1.min(low.1)),
+                (max.0.max(high.0), max.1.max(high.1)),
+            )
+        })
+        .or(area)
+        .unwrap_or(((0, row), (0, row)));
+    let mut svg = Svg::new(min, max);
+
+    for sensor in &coverage.sensors {
+        draw_sensor(&mut svg, sensor);
+    }
+
+    if let Some((min, max)) = area {
+        svg.rect(
+            svg.clamp((min.0 as f64 - 0.5, min.1 as f64 - 0.5)),
+            svg.clamp((max.0 as f64 + 0.5, max.1 as f64 + 0.5)),
+            r#"fill="none" stroke="black" stroke-width="2" stroke-dasharray="8 4" clip-path="url(#view)""#,
+        );
+    }
+
+    let y = row as f64;
+    if svg.contains((svg.min.0, y)) {
+        svg.line(
+            (svg.min.0, y),
+            (svg.max.0, y),
+            r#"stroke="black" stroke-width="1""#,
+        );
+        for (start, end) in coverage.row(row) {
+            svg.line(
+                svg.clamp((start as f64 - 0.5, y)),
+                svg.clamp((end as f64 + 0.5, y)),
+                r#"stroke="red" stroke-width="4" stroke-opacity="0.7" clip-path="url(#view)""#,
+            );
+        }
+    }
+
+    svg.finish()
+}
+
+type SensorMetric = Manhattan;
+
 const ROW: i32 = 2000000;
+// box to analyse after answering for the row, as min and max corners
+const AREA: Option<((i32, i32), (i32, i32))> = Some(((0, 0), (4000000, 4000000)));
 
 fn main() {
     let lines = stdin().lock().lines();
-    let lines = lines.flatten().enumerate().map(|(n, s)| {
+    let lines = lines.map_while(Result::ok).enumerate().map(|(n, s)| {
         line::<i32, VerboseError<_>>(&s)
             .finish()
             .map(|(_, line)| line)
@@ -99,9 +446,11 @@ fn main() {
             })
     });
 
-    let mut beacons = HashSet::new();
+    let mut coverage = Coverage::<SensorMetric> {
+        sensors: Vec::new(),
+        beacons: HashSet::new(),
+    };
 
-    let mut intervals = BTreeSet::new();
     for line in lines {
         let (x1, y1, x2, y2) = match line {
             Ok(line) => line,
@@ -113,18 +462,31 @@ fn main() {
                 return;
             }
         };
-        if y1 == ROW {
-            beacons.insert(x1);
-        }
-        if y2 == ROW {
-            beacons.insert(x2);
-        }
-        let distance = (x1 - x2).abs() + (y1 - y2).abs();
-        let remains = distance - (y1 - ROW).abs();
-        if remains >= 0 {
-            intervals.add(x1 - remains, x1 + remains);
-        }
+        coverage.sensors.push(Sensor::new((x1, y1), (x2, y2)));
+        coverage.beacons.insert((x2, y2));
     }
 
-    println!("{}", intervals.count() - beacons.len());
+    let covered = count(&coverage.row(ROW), i32::MIN, i32::MAX);
+    let beacons = coverage.beacons((i32::MIN, ROW), (i32::MAX, ROW));
+    println!("{}", covered - beacons);
+
+    if let Some((min, max)) = AREA {
+        let covered = coverage.area(min, max);
+        let beacons = coverage.beacons(min, max);
+        let total = (max.0 - min.0 + 1) as usize * (max.1 - min.1 + 1) as usize;
+        println!(
+            "between {:?} and {:?}: {} covered, {} of them known beacons, {} uncovered",
+            min,
+            max,
+            covered,
+            beacons,
+            total - covered
+        );
+    }
+
+    if let Some(path) = args().nth(1) {
+        if let Err(err) = fs::write(&path, svg(&coverage, ROW, AREA)) {
+            println!("Could not write {}: {}", path, err);
+        }
+    }
 }
@@ -1,4 +1,8 @@
+use std::env::args;
+use std::fmt::Write;
+use std::fs;
 use std::io::{stdin, BufRead};
+use std::marker::PhantomData;
 use std::ops::Neg;
 use std::str::FromStr;
 
@@ -49,102 +53,528 @@ fn line<'a, T: Number, E: ExpressionParseError<'a, T>>(
     Ok((i, (x1, y1, x2, y2)))
 }
 
+// coverage of a sensor is a square in coordinates (u, v) specific to the metric. Actual positions
+// are the (u, v) where u and v are congruent modulo STEP
+trait Metric {
+    const STEP: i64;
+
+    fn distance(a: (i64, i64), b: (i64, i64)) -> i64;
+    fn to_uv(position: (i64, i64)) -> (i64, i64);
+    fn from_uv(uv: (i64, i64)) -> (i64, i64);
+    // same as from_uv, for drawing
+    fn corner(uv: (f64, f64)) -> (f64, f64);
+    // inclusive range of v at the given u for positions between min and max
+    fn v_range_at(min: (i64, i64), max: (i64, i64), u: i64) -> (i64, i64);
+}
+
+// the diamond of a sensor becomes a square once rotated by 45°, u = x + y and v = x - y
+#[derive(Debug)]
+struct Manhattan;
+
+impl Metric for Manhattan {
+    const STEP: i64 = 2;
+
+    fn distance(a: (i64, i64), b: (i64, i64)) -> i64 {
+        (a.0 - b.0).abs() + (a.1 - b.1).abs()
+    }
+
+    fn to_uv((x, y): (i64, i64)) -> (i64, i64) {
+        (x + y, x - y)
+    }
+
+    fn from_uv((u, v): (i64, i64)) -> (i64, i64) {
+        ((u + v) / 2, (u - v) / 2)
+    }
+
+    fn corner((u, v): (f64, f64)) -> (f64, f64) {
+        ((u + v) / 2., (u - v) / 2.)
+    }
+
+    fn v_range_at(min: (i64, i64), max: (i64, i64), u: i64) -> (i64, i64) {
+        (
+            (2 * min.0 - u).max(u - 2 * max.1),
+            (2 * max.0 - u).min(u - 2 * min.1),
+        )
+    }
+}
+
+// coverage is already a square, u = x and v = y
+#[allow(dead_code)]
 #[derive(Debug)]
-struct Sensor {
+struct Chebyshev;
+
+impl Metric for Chebyshev {
+    const STEP: i64 = 1;
+
+    fn distance(a: (i64, i64), b: (i64, i64)) -> i64 {
+        (a.0 - b.0).abs().max((a.1 - b.1).abs())
+    }
+
+    fn to_uv(position: (i64, i64)) -> (i64, i64) {
+        position
+    }
+
+    fn from_uv(uv: (i64, i64)) -> (i64, i64) {
+        uv
+    }
+
+    fn corner(uv: (f64, f64)) -> (f64, f64) {
+        uv
+    }
+
+    fn v_range_at(min: (i64, i64), max: (i64, i64), _: i64) -> (i64, i64) {
+        (min.1, max.1)
+    }
+}
+
+#[derive(Debug)]
+struct Sensor<M: Metric> {
     location: (i64, i64),
+    beacon: (i64, i64),
     distance: i64,
+    metric: PhantomData<M>,
 }
 
-impl Sensor {
+impl<M: Metric> Sensor<M> {
     fn new(location: (i64, i64), beacon: (i64, i64)) -> Self {
         Self {
             location,
-            distance: (location.0 - beacon.0).abs() + (location.1 - beacon.1).abs(),
+            beacon,
+            distance: M::distance(location, beacon),
+            metric: PhantomData,
         }
     }
-    fn zero_crossings(&self) -> ((i64, i64), (i64, i64)) {
-        let (ascending, descending) =
-            zero_crossing(self.location.0 - self.distance, self.location.1);
-        let (ascending1, descending1) =
-            zero_crossing(self.location.0 + self.distance, self.location.1);
-        ((ascending, ascending1), (descending, descending1))
-    }
-
-    fn intersection_neighbours(&self, other: &Self) -> Vec<(i64, i64)> {
-        let (self_ascending, self_descending) = self.zero_crossings();
-        let (other_ascending, other_descending) = other.zero_crossings();
-        let mut intersection_neighbours = [
-            intersection(self_ascending.0, other_descending.0),
-            intersection(self_ascending.1, other_descending.0),
-            intersection(self_ascending.0, other_descending.1),
-            intersection(self_ascending.1, other_descending.1),
-            intersection(other_ascending.0, self_descending.0),
-            intersection(other_ascending.1, self_descending.0),
-            intersection(other_ascending.0, self_descending.1),
-            intersection(other_ascending.1, self_descending.1),
+
+    // inclusive ranges of u and v covered by the sensor
+    fn square(&self) -> ((i64, i64), (i64, i64)) {
+        let (u, v) = M::to_uv(self.location);
+        (
+            (u - self.distance, u + self.distance),
+            (v - self.distance, v + self.distance),
+        )
+    }
+
+    fn covers(&self, (u, v): (i64, i64)) -> bool {
+        let ((u0, u1), (v0, v1)) = self.square();
+        (u0..=u1).contains(&u) && (v0..=v1).contains(&v)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct SearchBox {
+    min: (i64, i64),
+    max: (i64, i64),
+}
+
+impl SearchBox {
+    // inclusive ranges of u and v containing the box
+    fn uv_ranges<M: Metric>(&self) -> ((i64, i64), (i64, i64)) {
+        [
+            self.min,
+            (self.min.0, self.max.1),
+            (self.max.0, self.min.1),
+            self.max,
         ]
+        .map(M::to_uv)
         .into_iter()
-        .flat_map(|(x, y)| get_neighbours(x, y))
-        .collect::<Vec<_>>();
-        intersection_neighbours.sort();
-        intersection_neighbours.dedup();
-        intersection_neighbours
+        .fold(
+            ((i64::MAX, i64::MIN), (i64::MAX, i64::MIN)),
+            |((u0, u1), (v0, v1)), (u, v)| ((u0.min(u), u1.max(u)), (v0.min(v), v1.max(v))),
+        )
     }
 }
 
-// intersection of two perpendicular lines at 45 deg, first ascending, second descending, having
-// their x=0 at that given y
-fn intersection(y1: i64, y2: i64) -> (f64, f64) {
-    // crossings are correct, now check intersections
-    ((y1 - y2) as f64 / 2., y1 as f64 - ((y1 - y2) as f64 / 2.))
+// uncovered rectangle in the metric's coordinates, inclusive
+#[derive(Debug)]
+struct Region<M: Metric> {
+    u: (i64, i64),
+    v: (i64, i64),
+    metric: PhantomData<M>,
 }
 
-// give the two y position at x=zero, for the two lines crossing at x,y , first ascending, second descending
-fn zero_crossing(x: i64, y: i64) -> (i64, i64) {
-    (x + y, y - x)
+impl<M: Metric> Region<M> {
+    // v range of actual positions inside the box at the given u, empty if start > end
+    fn v_range_at(&self, u: i64, search_box: SearchBox) -> (i64, i64) {
+        let (min, max) = M::v_range_at(search_box.min, search_box.max, u);
+        let start = self.v.0.max(min);
+        let end = self.v.1.min(max);
+        (
+            start + (start - u).rem_euclid(M::STEP),
+            end - (end - u).rem_euclid(M::STEP),
+        )
+    }
+
+    fn positions(&self, search_box: SearchBox) -> impl Iterator<Item = (i64, i64)> + '_ {
+        (self.u.0..=self.u.1).flat_map(move |u| {
+            let (start, end) = self.v_range_at(u, search_box);
+            (start..=end)
+                .step_by(M::STEP as usize)
+                .map(move |v| M::from_uv((u, v)))
+        })
+    }
+
+    // corners of the smallest rectangle containing the region's positions. For a given u, x and y
+    // are monotonic in v so the ends of each range are enough
+    fn bounds(&self, search_box: SearchBox) -> ((i64, i64), (i64, i64)) {
+        (self.u.0..=self.u.1)
+            .flat_map(|u| match self.v_range_at(u, search_box) {
+                (start, end) if start <= end => vec![M::from_uv((u, start)), M::from_uv((u, end))],
+                _ => vec![],
+            })
+            .fold(
+                (search_box.max, search_box.min),
+                |((x0, y0), (x1, y1)), (x, y)| ((x0.min(x), y0.min(y)), (x1.max(x), y1.max(y))),
+            )
+    }
+
+    fn count(&self, search_box: SearchBox) -> usize {
+        (self.u.0..=self.u.1)
+            .map(|u| match self.v_range_at(u, search_box) {
+                (start, end) if start <= end => ((end - start) / M::STEP + 1) as usize,
+                _ => 0,
+            })
+            .sum()
+    }
 }
 
-fn get_neighbours(x: f64, y: f64) -> Vec<(i64, i64)> {
-    if x.fract() != 0. {
-        [
-            (x.floor() as i64, y.floor() as i64),
-            (x.floor() as i64, y.ceil() as i64),
-            (x.ceil() as i64, y.floor() as i64),
-            (x.ceil() as i64, y.ceil() as i64),
-        ]
-        .into()
-    } else {
-        [
-            (x as i64 - 1, y as i64 - 1),
-            (x as i64 - 1, y as i64 + 1),
-            (x as i64 + 1, y as i64 - 1),
-            (x as i64 + 1, y as i64 + 1),
-            (x as i64 - 1, y as i64),
-            (x as i64 + 1, y as i64),
-            (x as i64, y as i64 - 1),
-            (x as i64, y as i64 + 1),
-        ]
-        .into()
+// sorted starts of the ranges and the positions just after their ends, within min..=max + 1
+fn boundaries(ranges: impl Iterator<Item = (i64, i64)>, min: i64, max: i64) -> Vec<i64> {
+    let mut boundaries = ranges
+        .flat_map(|(start, end)| [start, end + 1])
+        .filter(|boundary| (min..=max).contains(boundary))
+        .chain([min, max + 1])
+        .collect::<Vec<_>>();
+    boundaries.sort();
+    boundaries.dedup();
+    boundaries
+}
+
+// every region of the box not covered by any sensor. Boundaries of the sensors' squares split the
+// box into a grid of rectangles that are either entirely covered or not, so only one corner of
+// each of them needs to be checked
+fn uncovered<M: Metric>(sensors: &[Sensor<M>], search_box: SearchBox) -> Vec<Region<M>> {
+    let ((u_min, u_max), (v_min, v_max)) = search_box.uv_ranges::<M>();
+
+    let us = boundaries(sensors.iter().map(|s| s.square().0), u_min, u_max);
+    let vs = boundaries(sensors.iter().map(|s| s.square().1), v_min, v_max);
+
+    let mut regions: Vec<Region<M>> = Vec::new();
+    let mut previous_strip: Vec<usize> = Vec::new();
+
+    for u in us.windows(2) {
+        let (u0, u1) = (u[0], u[1] - 1);
+        let mut strip: Vec<(i64, i64)> = Vec::new();
+
+        for v in vs.windows(2) {
+            let (v0, v1) = (v[0], v[1] - 1);
+            if sensors.iter().any(|sensor| sensor.covers((u0, v0))) {
+                continue;
+            }
+            match strip.last_mut() {
+                Some(last) if last.1 + 1 == v0 => last.1 = v1,
+                _ => strip.push((v0, v1)),
+            }
+        }
+
+        let mut current_strip = Vec::new();
+        for v in strip {
+            let extended = previous_strip
+                .iter()
+                .copied()
+                .find(|&index| regions[index].v == v && regions[index].u.1 + 1 == u0);
+            match extended {
+                Some(index) => {
+                    regions[index].u.1 = u1;
+                    current_strip.push(index);
+                }
+                None => {
+                    current_strip.push(regions.len());
+                    regions.push(Region {
+                        u: (u0, u1),
+                        v,
+                        metric: PhantomData,
+                    });
+                }
+            }
+        }
+        previous_strip = current_strip;
     }
+
+    regions.retain(|region| region.count(search_box) > 0);
+    regions
+}
+
+// width and height of the svg picture, in pixels
+const SVG_SIZE: f64 = 1000.;
+const SVG_MARGIN: f64 = 20.;
+
+// svg picture mapping puzzle coordinates between min and max into the picture. Positions are the
+// center of their cell, so shapes covering cells are extended by half a cell
+struct Svg {
+    min: (f64, f64),
+    max: (f64, f64),
+    scale: f64,
+    body: String,
 }
 
-fn first_match(position: (i64, i64), sensors: &Vec<Sensor>) -> Option<&Sensor> {
+impl Svg {
+    fn new(min: (i64, i64), max: (i64, i64)) -> Self {
+        let extent = (max.0 - min.0).max(max.1 - min.1) as f64 + 1.;
+        let mut svg = Self {
+            min: (min.0 as f64 - 0.5, min.1 as f64 - 0.5),
+            max: (max.0 as f64 + 0.5, max.1 as f64 + 0.5),
+            scale: (SVG_SIZE - 2. * SVG_MARGIN) / extent,
+            body: String::new(),
+        };
+        svg.clip_path("view", svg.min, svg.max);
+        svg
+    }
+
+    // brings a point within a cell around the view, so that shapes reaching far out of it stay
+    // reasonably sized once clipped to `url(#view)`
+    fn clamp(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (
+            x.clamp(self.min.0 - 1., self.max.0 + 1.),
+            y.clamp(self.min.1 - 1., self.max.1 + 1.),
+        )
+    }
+
+    fn contains(&self, (x, y): (f64, f64)) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
+    }
+
+    fn point(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (
+            (x - self.min.0) * self.scale + SVG_MARGIN,
+            (y - self.min.1) * self.scale + SVG_MARGIN,
+        )
+    }
+
+    // cells are drawn at least that many pixels wide
+    fn size(&self, pixels: f64) -> f64 {
+        pixels.max(self.scale)
+    }
+
+    // the convex polygon cut to a cell around the view, one side of that box after the other
+    fn crop(&self, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        let (min, max) = (
+            (self.min.0 - 1., self.min.1 - 1.),
+            (self.max.0 + 1., self.max.1 + 1.),
+        );
+        // how far inside each side a point is
+        let sides: [&dyn Fn((f64, f64)) -> f64; 4] = [
+            &|(x, _)| x - min.0,
+            &|(x, _)| max.0 - x,
+            &|(_, y)| y - min.1,
+            &|(_, y)| max.1 - y,
+        ];
+        sides.iter().fold(points.to_vec(), |points, inside| {
+            let mut cropped = vec![];
+            for (i, &a) in points.iter().enumerate() {
+                let b = points[(i + 1) % points.len()];
+                let (da, db) = (inside(a), inside(b));
+                if da >= 0. {
+                    cropped.push(a);
+                }
+                if (da >= 0.) != (db >= 0.) {
+                    let t = da / (da - db);
+                    cropped.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+                }
+            }
+            cropped
+        })
+    }
+
+    fn polygon(&mut self, points: &[(f64, f64)], style: &str) {
+        let points = self.crop(points);
+        if points.is_empty() {
+            return;
+        }
+        let points = points
+            .iter()
+            .map(|point| {
+                let (x, y) = self.point(*point);
+                format!("{:.2},{:.2}", x, y)
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(self.body, r#"<polygon points="{}" {}/>"#, points, style).unwrap();
+    }
+
+    fn rect(&mut self, min: (f64, f64), max: (f64, f64), style: &str) {
+        let (x0, y0) = self.point(min);
+        let (x1, y1) = self.point(max);
+        writeln!(
+            self.body,
+            r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" {}/>"#,
+            x0,
+            y0,
+            x1 - x0,
+            y1 - y0,
+            style
+        )
+        .unwrap();
+    }
+
+    fn clip_path(&mut self, id: &str, min: (f64, f64), max: (f64, f64)) {
+        writeln!(self.body, r#"<clipPath id="{}">"#, id).unwrap();
+        self.rect(min, max, "");
+        writeln!(self.body, "</clipPath>").unwrap();
+    }
+
+    fn line(&mut self, from: (f64, f64), to: (f64, f64), style: &str) {
+        let (x0, y0) = self.point(from);
+        let (x1, y1) = self.point(to);
+        writeln!(
+            self.body,
+            r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" {}/>"#,
+            x0, y0, x1, y1, style
+        )
+        .unwrap();
+    }
+
+    // marker of a cell, centered on the position
+    fn cell(&mut self, (x, y): (i64, i64), pixels: f64, style: &str) {
+        let (x, y) = self.point((x as f64, y as f64));
+        let size = self.size(pixels);
+        writeln!(
+            self.body,
+            r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" {}/>"#,
+            x - size / 2.,
+            y - size / 2.,
+            size,
+            size,
+            style
+        )
+        .unwrap();
+    }
+
+    fn circle(&mut self, (x, y): (i64, i64), pixels: f64, style: &str) {
+        let (x, y) = self.point((x as f64, y as f64));
+        writeln!(
+            self.body,
+            r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" {}/>"#,
+            x,
+            y,
+            self.size(pixels) / 2.,
+            style
+        )
+        .unwrap();
+    }
+
+    fn finish(self) -> String {
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {size} {size}">
+<rect width="100%" height="100%" fill="white"/>
+{}</svg>
+"#,
+            self.body,
+            size = SVG_SIZE
+        )
+    }
+}
+
+// polygon of the rectangle between (u0, v0) and (u1, v1) in the metric's coordinates
+fn outline<M: Metric>((u0, u1): (f64, f64), (v0, v1): (f64, f64)) -> [(f64, f64); 4] {
+    [(u0, v0), (u0, v1), (u1, v1), (u1, v0)].map(M::corner)
+}
+
+// area covered by the sensor, with the sensor and a line to its closest beacon
+fn draw_sensor<M: Metric>(svg: &mut Svg, sensor: &Sensor<M>) {
+    let ((u0, u1), (v0, v1)) = sensor.square();
+    svg.polygon(
+        &outline::<M>(
+            (u0 as f64 - 0.5, u1 as f64 + 0.5),
+            (v0 as f64 - 0.5, v1 as f64 + 0.5),
+        ),
+        r#"fill="steelblue" fill-opacity="0.15" stroke="steelblue" stroke-width="1" clip-path="url(#view)""#,
+    );
+    svg.line(
+        (sensor.location.0 as f64, sensor.location.1 as f64),
+        (sensor.beacon.0 as f64, sensor.beacon.1 as f64),
+        r#"stroke="gray" stroke-width="1" stroke-dasharray="4 2""#,
+    );
+    svg.circle(sensor.location, 6., r#"fill="steelblue""#);
+    svg.cell(sensor.beacon, 6., r#"fill="darkorange""#);
+}
+
+// the view fits the sensors and beacons, the search box is only drawn where it crosses it
+fn svg<M: Metric>(sensors: &[Sensor<M>], search_box: SearchBox, regions: &[Region<M>]) -> String {
+    let (min, max) = sensors
+        .iter()
+        .flat_map(|sensor| [sensor.location, sensor.beacon])
+        .map(|position| (position, position))
+        .reduce(|(min, max), (low, high)| {
+            (
+                (min.0.min(low.0), min.1.min(low.1)),
+                (max.0.max(high.0), max.1.max(high.1)),
+            )
+        })
+        .unwrap_or((search_box.min, search_box.max));
+    let mut svg = Svg::new(min, max);
+
     for sensor in sensors {
-        let distance =
-            (position.0 - sensor.location.0).abs() + (position.1 - sensor.location.1).abs();
-        if distance <= sensor.distance {
-            return Some(sensor);
+        draw_sensor(&mut svg, sensor);
+    }
+
+    let (box_min, box_max) = (
+        svg.clamp((search_box.min.0 as f64 - 0.5, search_box.min.1 as f64 - 0.5)),
+        svg.clamp((search_box.max.0 as f64 + 0.5, search_box.max.1 as f64 + 0.5)),
+    );
+    svg.rect(
+        box_min,
+        box_max,
+        r#"fill="none" stroke="black" stroke-width="2" stroke-dasharray="8 4" clip-path="url(#view)""#,
+    );
+    // the part of the search box within the view
+    svg.clip_path(
+        "search-box",
+        (box_min.0.max(svg.min.0), box_min.1.max(svg.min.1)),
+        (box_max.0.min(svg.max.0), box_max.1.min(svg.max.1)),
+    );
+
+    for region in regions {
+        if region.count(search_box) <= LIST_LIMIT {
+            for position in region.positions(search_box) {
+                if !svg.contains((position.0 as f64, position.1 as f64)) {
+                    continue;
+                }
+                svg.circle(
+                    position,
+                    12.,
+                    r#"fill="red" stroke="black" stroke-width="1""#,
+                );
+            }
+        } else {
+            // a position spans half a step around it in the metric's coordinates
+            let margin = M::STEP as f64 / 2.;
+            let corners = outline::<M>(
+                (region.u.0 as f64 - margin, region.u.1 as f64 + margin),
+                (region.v.0 as f64 - margin, region.v.1 as f64 + margin),
+            );
+            svg.polygon(
+                &corners,
+                r#"fill="red" fill-opacity="0.5" stroke="red" clip-path="url(#search-box)""#,
+            );
         }
     }
 
-    None
+    svg.finish()
 }
 
+type SensorMetric = Manhattan;
+
 const DIM: i64 = 4000000;
+// regions up to that size get their positions listed
+const LIST_LIMIT: usize = 16;
+
+fn tuning_frequency((x, y): (i64, i64)) -> i64 {
+    x * 4000000 + y
+}
 
 fn main() {
     let lines = stdin().lock().lines();
-    let lines = lines.flatten().enumerate().map(|(n, s)| {
+    let lines = lines.map_while(Result::ok).enumerate().map(|(n, s)| {
         line::<i64, VerboseError<_>>(&s)
             .finish()
             .map(|(_, line)| line)
@@ -173,42 +603,55 @@ fn main() {
             }
         };
 
-        sensors.push(Sensor::new((x1, y1), (x2, y2)));
+        sensors.push(Sensor::<SensorMetric>::new((x1, y1), (x2, y2)));
     }
 
-    for sensor1 in &sensors {
-        for sensor2 in sensors.iter().skip(1) {
-            for (x, y) in sensor1.intersection_neighbours(sensor2) {
-                if !(0..=DIM).contains(&x) || !(0..=DIM).contains(&y) {
-                    continue;
-                }
-                if first_match((x, y), &sensors).is_none() {
-                    let result = x * 4000000 + y;
-                    println!("{}", result);
-                    return;
+    let search_box = SearchBox {
+        min: (0, 0),
+        max: (DIM, DIM),
+    };
+    let regions = uncovered(&sensors, search_box);
+    let total = regions
+        .iter()
+        .map(|region| region.count(search_box))
+        .sum::<usize>();
+
+    match total {
+        0 => println!(
+            "no uncovered position between {:?} and {:?}",
+            search_box.min, search_box.max
+        ),
+        1 => {
+            let position = regions[0]
+                .positions(search_box)
+                .next()
+                .expect("region has one position");
+            println!("{}", tuning_frequency(position));
+        }
+        _ => {
+            println!("{} uncovered positions in {} regions", total, regions.len());
+            for region in &regions {
+                let count = region.count(search_box);
+                if count <= LIST_LIMIT {
+                    for position in region.positions(search_box) {
+                        println!("\t{:?}: {}", position, tuning_frequency(position));
+                    }
+                } else {
+                    let ((x0, y0), (x1, y1)) = region.bounds(search_box);
+                    println!(
+                        "\t{} positions between {:?} and {:?}",
+                        count,
+                        (x0, y0),
+                        (x1, y1)
+                    );
                 }
             }
         }
-        let (ascending, descending) = sensor1.zero_crossings();
-        for (x, y) in [
-            (0, ascending.0),
-            (0, ascending.1),
-            (ascending.0, 0),
-            (ascending.1, 0),
-            (0, descending.0),
-            (0, descending.1),
-            (-descending.0, 0),
-            (-descending.1, 0),
-        ] {
-            if !(0..=DIM).contains(&x) || !(0..=DIM).contains(&y) {
-                continue;
-            }
+    }
 
-            if first_match((x, y), &sensors).is_none() {
-                let result = x * 4000000 + y;
-                println!("{}", result);
-                return;
-            }
+    if let Some(path) = args().nth(1) {
+        if let Err(err) = fs::write(&path, svg(&sensors, search_box, &regions)) {
+            println!("Could not write {}: {}", path, err);
         }
     }
 }
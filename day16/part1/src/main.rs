@@ -1,10 +1,11 @@
-use std::cmp::Ordering;
-use std::collections::hash_map::{Entry, RandomState};
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::env::args;
 use std::error::Error;
-use std::fmt::{Debug, Display, Formatter};
+use std::fmt::{Debug, Display, Formatter, Write};
+use std::fs;
 use std::io::{stdin, BufRead};
-use std::ops::{Deref, Neg};
+use std::ops::Deref;
 use std::str::{from_utf8, FromStr};
 
 use nom::character::complete::alphanumeric1;
@@ -19,8 +20,6 @@ use nom::{
     Finish, IResult,
 };
 
-trait Number: FromStr + Neg<Output = Self> {}
-
 trait ExpressionParseError<'a, T: FromStr>:
     ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, <T as FromStr>::Err>
 {
@@ -97,169 +96,401 @@ fn line<'a, E: ExpressionParseError<'a, usize>>(i: &'a str) -> IResult<&'a str,
     ))
 }
 
-#[derive(Clone)]
-struct State {
-    position: Label,
-    score: usize,
-    opened: HashSet<Label>,
-    time: usize,
+// valves with a flow rate and the shortest distances between them. Sets of valves are bitmasks
+// of their index
+struct Network {
+    valves: Vec<(Label, usize)>,
+    distances: Vec<Vec<usize>>,
+    from_start: Vec<usize>,
+    // every valve, and the next one on the shortest path between any two of them
+    labels: Vec<Label>,
+    next: Vec<Vec<usize>>,
+    // position in labels of the start and of the valves with a flow rate
+    start: usize,
+    indices: Vec<usize>,
 }
 
-const TIMEOUT: usize = 29;
+const MAX_VALVES: usize = 20;
+
+impl Network {
+    fn new(valves: &HashMap<Label, Valve>, start: Label) -> Result<Self, String> {
+        let mut labels = valves.keys().copied().collect::<Vec<_>>();
+        labels.sort();
+        let index = HashMap::<_, _, RandomState>::from_iter(
+            labels.iter().enumerate().map(|(i, label)| (*label, i)),
+        );
+
+        // Floyd-Warshall, unreachable valves stay at usize::MAX
+        let mut distances = vec![vec![usize::MAX; labels.len()]; labels.len()];
+        let mut next = vec![(0..labels.len()).collect::<Vec<_>>(); labels.len()];
+        for (i, label) in labels.iter().enumerate() {
+            distances[i][i] = 0;
+            for (distance, destination) in &valves[label].destinations {
+                let j = *index
+                    .get(destination)
+                    .ok_or_else(|| format!("unknown valve {}", destination))?;
+                distances[i][j] = distances[i][j].min(*distance);
+            }
+        }
+        for k in 0..labels.len() {
+            for i in 0..labels.len() {
+                for j in 0..labels.len() {
+                    let distance = distances[i][k].saturating_add(distances[k][j]);
+                    if distance < distances[i][j] {
+                        distances[i][j] = distance;
+                        next[i][j] = next[i][k];
+                    }
+                }
+            }
+        }
 
-impl State {
-    fn forward(&mut self, valves: &HashMap<Label, Valve>, time: usize) {
-        for valve in &self.opened {
-            self.score += valves.get(valve).unwrap().rate * time
+        let start = *index
+            .get(&start)
+            .ok_or_else(|| format!("start valve {} not found", start))?;
+        let useful = (0..labels.len())
+            .filter(|i| valves[&labels[*i]].rate > 0)
+            .collect::<Vec<_>>();
+        if useful.len() > MAX_VALVES {
+            return Err(format!(
+                "{} valves have a flow rate, at most {} are supported",
+                useful.len(),
+                MAX_VALVES
+            ));
         }
-        self.time += time
+
+        Ok(Self {
+            valves: useful
+                .iter()
+                .map(|i| (labels[*i], valves[&labels[*i]].rate))
+                .collect(),
+            distances: useful
+                .iter()
+                .map(|i| useful.iter().map(|j| distances[*i][*j]).collect())
+                .collect(),
+            from_start: useful.iter().map(|j| distances[start][*j]).collect(),
+            labels,
+            next,
+            start,
+            indices: useful,
+        })
     }
 
-    fn next(mut self, valves: &HashMap<Label, Valve>) -> Vec<State> {
-        if self.time >= TIMEOUT {
-            return vec![];
-        }
+    // time left once the valve is reached and opened, if there's any
+    fn remaining(&self, position: Option<usize>, valve: usize, time: usize) -> Option<usize> {
+        let distance = match position {
+            Some(position) => self.distances[position][valve],
+            None => self.from_start[valve],
+        };
+        // moving there, then one minute to open it
+        time.checked_sub(distance.saturating_add(1))
+            .filter(|time| *time > 0)
+    }
 
-        let current = valves.get(&self.position).unwrap();
+    // most pressure released for every time budget up to the given one, and the set of valves opened
+    // to release the most within the whole time
+    fn best(&self, time: usize) -> (Vec<usize>, usize) {
+        let mut best = vec![0; time + 1];
+        let mut set = 0;
+        self.explore(None, time, 0, 0, 0, &mut best, &mut set);
+        (best, set)
+    }
 
-        if !self.opened.contains(&self.position) {
-            self.opened.insert(self.position);
-            self.forward(valves, 1);
-            return vec![self];
+    // `rate` is the flow of the opened valves and `offset` what they lost by not being open from the
+    // start, so that they release `rate * budget - offset` within any budget past the last opening
+    #[allow(clippy::too_many_arguments)]
+    fn explore(
+        &self,
+        position: Option<usize>,
+        time: usize,
+        opened: usize,
+        rate: usize,
+        offset: usize,
+        best: &mut [usize],
+        set: &mut usize,
+    ) {
+        let elapsed = best.len() - 1 - time;
+        // once another valve is open the path through it releases at least as much, so only the
+        // budgets before the earliest one are left to this node
+        let mut until = best.len();
+
+        for (valve, (_, flow)) in self.valves.iter().enumerate() {
+            if opened & (1 << valve) != 0 {
+                continue;
+            }
+            let Some(remaining) = self.remaining(position, valve, time) else {
+                continue;
+            };
+            until = until.min(best.len() - 1 - remaining);
+            self.explore(
+                Some(valve),
+                remaining,
+                opened | 1 << valve,
+                rate + flow,
+                offset + flow * (best.len() - 1 - remaining),
+                best,
+                set,
+            );
         }
 
-        if self.opened.len() < valves.len() {
-            let mut result = Vec::new();
-            for (distance, destination) in &current.destinations {
-                if !self.opened.contains(destination) && self.time + distance <= TIMEOUT {
-                    let mut new_state = self.clone();
-                    new_state.position = *destination;
-                    new_state.forward(valves, *distance);
-                    result.push(new_state);
-                }
+        let time = best.len() - 1;
+        if until == best.len() && rate * time - offset > best[time] {
+            *set = opened;
+        }
+
+        for (budget, best) in best.iter_mut().enumerate().take(until).skip(elapsed) {
+            *best = (*best).max(rate * budget - offset);
+        }
+    }
+    // pressure released and order in which to open valves, among the given set, to release the most
+    fn plan(&self, position: Option<usize>, time: usize, within: usize) -> (usize, Vec<usize>) {
+        let mut best = (0, vec![]);
+        for (valve, (_, rate)) in self.valves.iter().enumerate() {
+            if within & (1 << valve) == 0 {
+                continue;
             }
-            if !result.is_empty() {
-                return result;
+            let Some(time) = self.remaining(position, valve, time) else {
+                continue;
+            };
+            let (pressure, mut order) = self.plan(Some(valve), time, within ^ (1 << valve));
+            if pressure + rate * time > best.0 {
+                order.insert(0, valve);
+                best = (pressure + rate * time, order);
             }
         }
+        best
+    }
+
+    // minute by minute actions to open the valves in the given order
+    fn timeline(&self, order: &[usize]) -> Vec<Action> {
+        let mut position = self.start;
+        let mut actions = vec![];
+        for valve in order {
+            let destination = self.indices[*valve];
+            while position != destination {
+                position = self.next[position][destination];
+                actions.push(Action::Move(self.labels[position]));
+            }
+            actions.push(Action::Open(self.valves[*valve]));
+        }
+        actions
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Action {
+    Move(Label),
+    Open((Label, usize)),
+}
 
-        self.forward(valves, TIMEOUT - self.time);
-        vec![self]
+fn list(labels: &[Label]) -> String {
+    match labels {
+        [] => String::new(),
+        [label] => label.to_string(),
+        [first, second] => format!("{} and {}", first, second),
+        [rest @ .., last] => format!(
+            "{}, and {}",
+            rest.iter()
+                .map(Label::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            last
+        ),
     }
 }
 
-impl PartialEq<Self> for State {
-    fn eq(&self, other: &Self) -> bool {
-        self.cmp(other) == Ordering::Equal
+// graphviz graph of the tunnels, or when compressed of the distances between the start and the
+// valves with a flow rate. Valves opened in the timelines are highlighted, as well as the ones and
+// the tunnels they go through
+fn dot(
+    valves: &HashMap<Label, Valve>,
+    network: &Network,
+    timelines: &[(String, Vec<Action>)],
+    compressed: bool,
+) -> String {
+    let start = network.labels[network.start];
+    let pair = |a: Label, b: Label| (a.min(b), a.max(b));
+
+    let mut visited = HashSet::new();
+    let mut opened = HashSet::new();
+    // tunnels taken, and moves between opened valves
+    let mut taken = HashSet::new();
+    let mut legs = HashSet::new();
+    for (_, actions) in timelines {
+        let (mut position, mut valve) = (start, start);
+        for action in actions {
+            match action {
+                Action::Move(label) => {
+                    visited.insert(*label);
+                    taken.insert(pair(position, *label));
+                    position = *label;
+                }
+                Action::Open((label, _)) => {
+                    opened.insert(*label);
+                    legs.insert(pair(valve, *label));
+                    valve = *label;
+                }
+            };
+        }
+    }
+
+    let mut nodes = if compressed {
+        let mut nodes = network
+            .valves
+            .iter()
+            .map(|(label, _)| *label)
+            .collect::<Vec<_>>();
+        if !nodes.contains(&start) {
+            nodes.insert(0, start);
+        }
+        nodes
+    } else {
+        network.labels.clone()
+    };
+    nodes.sort();
+
+    let mut output = String::from("graph valves {\n");
+    for label in &nodes {
+        let mut attributes = format!(r#"label="{}\n{}""#, label, valves[label].rate);
+        if *label == start {
+            attributes.push_str(", shape=doublecircle");
+        }
+        if opened.contains(label) {
+            attributes.push_str(", style=filled, fillcolor=gold");
+        } else if visited.contains(label) {
+            attributes.push_str(", style=filled, fillcolor=lightyellow");
+        }
+        writeln!(output, "    {} [{}];", label, attributes).unwrap();
+    }
+
+    if compressed {
+        let index = |label: &Label| network.valves.iter().position(|(valve, _)| valve == label);
+        for (i, a) in nodes.iter().enumerate() {
+            for b in &nodes[i + 1..] {
+                let distance = match (index(a), index(b)) {
+                    (Some(a), Some(b)) => network.distances[a][b],
+                    (None, Some(valve)) | (Some(valve), None) => network.from_start[valve],
+                    (None, None) => unreachable!("only the start can be without flow rate"),
+                };
+                if distance == usize::MAX {
+                    continue;
+                }
+                let width = if legs.contains(&pair(*a, *b)) {
+                    ", penwidth=3"
+                } else {
+                    ""
+                };
+                writeln!(
+                    output,
+                    r#"    {} -- {} [label="{}"{}];"#,
+                    a, b, distance, width
+                )
+                .unwrap();
+            }
+        }
+    } else {
+        let mut tunnels = HashSet::new();
+        for label in &nodes {
+            for (_, destination) in &valves[label].destinations {
+                let tunnel = pair(*label, *destination);
+                if tunnels.insert(tunnel) {
+                    let attributes = if taken.contains(&tunnel) {
+                        " [penwidth=3]"
+                    } else {
+                        ""
+                    };
+                    writeln!(output, "    {} -- {}{};", label, destination, attributes).unwrap();
+                }
+            }
+        }
     }
+
+    output.push_str("}\n");
+    output
 }
 
-impl Eq for State {}
+// prints the timelines of the agents the way the puzzle describes them
+fn walkthrough(timelines: &[(String, Vec<Action>)], time: usize) {
+    let mut opened: Vec<(Label, usize)> = vec![];
+
+    for minute in 0..time {
+        println!("== Minute {} ==", minute + 1);
+        opened.sort();
+        let labels = opened.iter().map(|(label, _)| *label).collect::<Vec<_>>();
+        let pressure = opened.iter().map(|(_, rate)| rate).sum::<usize>();
+        match labels.len() {
+            0 => println!("No valves are open."),
+            1 => println!(
+                "Valve {} is open, releasing {} pressure.",
+                labels[0], pressure
+            ),
+            _ => println!(
+                "Valves {} are open, releasing {} pressure.",
+                list(&labels),
+                pressure
+            ),
+        }
 
-impl PartialOrd<Self> for State {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+        for (agent, actions) in timelines {
+            // "You" is the only agent whose verbs aren't in the third person
+            let s = if *agent == "You" { "" } else { "s" };
+            match actions.get(minute) {
+                Some(Action::Move(label)) => println!("{} move{} to valve {}.", agent, s, label),
+                Some(Action::Open(valve)) => {
+                    println!("{} open{} valve {}.", agent, s, valve.0);
+                    opened.push(*valve);
+                }
+                None => {}
+            }
+        }
+        println!();
     }
 }
 
-impl Ord for State {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.score
-            .cmp(&self.score)
-            .reverse()
-            .then_with(|| (self.time).cmp(&(other.time)))
+// most pressure released within every budget, and what the last minute added
+fn budgets(maxima: &[usize]) {
+    for minutes in 1..maxima.len() {
+        println!(
+            "{:>2} minutes: {:>5} (+{})",
+            minutes,
+            maxima[minutes],
+            maxima[minutes] - maxima[minutes - 1]
+        );
     }
 }
 
+const TIMEOUT: usize = 30;
+// only valves with a flow rate and the distances between them in the graphviz output
+const DOT_COMPRESSED: bool = false;
+
 fn main() -> Result<(), Box<dyn Error>> {
     let lines = stdin().lock().lines();
-    let lines = lines.flatten().map(|s| {
+    let lines = lines.map_while(Result::ok).map(|s| {
         line::<VerboseError<_>>(&s)
             .finish()
             .map(|(_, line)| line)
             .map_err(|err| err.to_string())
     });
 
-    let mut valves = lines
+    let valves = lines
         .map(|line| {
             let line = line?;
             Ok((line.label, line))
         })
         .collect::<Result<HashMap<Label, Valve>, String>>()?;
 
-    let start = Label::new([b'A', b'A']);
-    let mut destinations: HashMap<_, _, RandomState> =
-        HashMap::from_iter(valves.keys().map(|k| ((*k, *k), 1)));
-    let mut journeys = Vec::from([vec![start]]);
-    while let Some(journey) = journeys.pop() {
-        let mut to_visit = HashSet::new();
-        let current = journey.last().unwrap();
-        let neighbours = &valves.get(current).unwrap().destinations;
-        for (distance, valve) in journey.iter().copied().rev().enumerate() {
-            for (_, destination) in neighbours {
-                match destinations.entry((valve, *destination)) {
-                    Entry::Occupied(entry) => {
-                        let entry = entry.into_mut();
-                        if *entry > distance + 1 {
-                            *entry = distance + 1;
-                            to_visit.insert(*destination);
-                        }
-                    }
-                    Entry::Vacant(entry) => {
-                        entry.insert(distance + 1);
-                        to_visit.insert(*destination);
-                    }
-                };
-            }
-        }
-
-        for visit in to_visit {
-            let mut new_journey = journey.clone();
-            new_journey.push(visit);
-            journeys.push(new_journey);
-        }
-    }
-
-    for valve in valves.values_mut() {
-        valve.destinations = destinations
-            .iter()
-            .filter_map(|((source, destination), distance)| {
-                if source == &valve.label {
-                    Some((*distance, *destination))
-                } else {
-                    None
-                }
-            })
-            .collect();
-    }
+    let network = Network::new(&valves, Label::new([b'A', b'A']))?;
+    let (maxima, set) = network.best(TIMEOUT);
+    println!("{}", maxima[TIMEOUT]);
+    println!();
+    budgets(&maxima);
+    println!();
 
-    let mut max = 0;
+    let (_, order) = network.plan(None, TIMEOUT, set);
+    let timelines = [("You".to_string(), network.timeline(&order))];
+    walkthrough(&timelines, TIMEOUT);
 
-    let mut states = BinaryHeap::from([State {
-        position: start,
-        score: 0,
-        opened: valves
-            .values()
-            .filter_map(|valve| {
-                if valve.rate == 0 {
-                    Some(valve.label)
-                } else {
-                    None
-                }
-            })
-            .collect(),
-        time: 0,
-    }]);
-
-    while let Some(next) = states.pop() {
-        for state in next.next(&valves) {
-            if state.score > max {
-                max = state.score;
-            }
-            states.push(state)
-        }
+    if let Some(path) = args().nth(1) {
+        fs::write(path, dot(&valves, &network, &timelines, DOT_COMPRESSED))?;
     }
-
-    println!("{:?}", max);
     Ok(())
 }
@@ -1,6 +1,8 @@
+use std::env::args;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
-use std::io::{BufRead, stdin};
+use std::fs;
+use std::io::{stdin, BufRead};
 
 #[derive(Copy, Clone)]
 enum Jet {
@@ -41,25 +43,80 @@ impl TryFrom<u8> for Jet {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-enum Shape {
-    Horizontal,
-    Plus,
-    L,
-    Vertical,
-    Square,
-}
+// the puzzle's rocks, in the format of a definition file: drawings separated by empty lines
+const ROCKS: &str = "\
+####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##
+";
+
+// cells of every rock of the drawings, from the bottom left of the rock
+fn rocks(drawings: &str) -> Result<Vec<Vec<(usize, usize)>>, String> {
+    let mut rocks = vec![];
+    let mut rows = vec![];
+    for line in drawings.lines().map(str::trim).chain([""]) {
+        if !line.is_empty() {
+            rows.push(line);
+            continue;
+        }
+        if rows.is_empty() {
+            continue;
+        }
 
-impl From<Shape> for Vec<(usize, usize)> {
-    fn from(shape: Shape) -> Self {
-        match shape {
-            Shape::Horizontal => vec![(0, 0), (1, 0), (2, 0), (3, 0)],
-            Shape::Plus => vec![(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)],
-            Shape::L => vec![(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)],
-            Shape::Vertical => vec![(0, 0), (0, 1), (0, 2), (0, 3)],
-            Shape::Square => vec![(0, 0), (1, 0), (0, 1), (1, 1)],
+        let mut rock = vec![];
+        for (y, row) in rows.drain(..).rev().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    '#' => rock.push((x, y)),
+                    '.' => {}
+                    _ => {
+                        return Err(format!(
+                            "invalid character {:?} in rock {}",
+                            c,
+                            rocks.len() + 1
+                        ))
+                    }
+                }
+            }
         }
+
+        // drawings may have empty rows or columns around the rock
+        let min_x = rock.iter().map(|(x, _)| *x).min();
+        let min_y = rock.iter().map(|(_, y)| *y).min();
+        let (Some(min_x), Some(min_y)) = (min_x, min_y) else {
+            return Err(format!("rock {} is empty", rocks.len() + 1));
+        };
+        rock.iter_mut().for_each(|(x, y)| {
+            *x -= min_x;
+            *y -= min_y;
+        });
+        if rock.iter().any(|(x, _)| START_LEFT + x >= WIDTH) {
+            return Err(format!(
+                "rock {} doesn't fit in the chamber",
+                rocks.len() + 1
+            ));
+        }
+        rocks.push(rock);
     }
+
+    if rocks.is_empty() {
+        return Err("no rocks defined".into());
+    }
+    Ok(rocks)
 }
 
 struct Repeater<'a, I>(&'a [I], usize);
@@ -81,18 +138,17 @@ impl<'a, I> From<&'a [I]> for Repeater<'a, I> {
 }
 
 const WIDTH: usize = 7;
+// distance between the left wall and a new rock
+const START_LEFT: usize = 2;
 const ITERATIONS: usize = 2022;
+// distance between the highest rock and a new one
 const START_HEIGHT: usize = 3;
-
-trait Blocks<I>
-where
-    Self: Sized,
-{
-    fn apply(self, j: Jet, bottom: &Self) -> Result<Self, Self>;
-    fn down(self, bottom: &Self) -> Result<Self, Self>;
-    fn merge_into(self, blocks: Self) -> Self;
-    fn max_height(&self) -> I;
-}
+// print the chamber after this many rocks
+const RENDER_AFTER: Option<usize> = None;
+// print every step of the fall of this rock, the first one being 1
+const RENDER_FALL: Option<usize> = None;
+// rows printed from the top of the chamber
+const RENDER_ROWS: usize = 20;
 
 trait AnyResult<I> {
     fn any(self) -> I;
@@ -107,94 +163,124 @@ impl<I> AnyResult<I> for Result<I, I> {
     }
 }
 
-impl Blocks<usize> for Vec<(usize, usize)> {
-    fn apply(mut self, d: Jet, bottom: &Self) -> Result<Self, Self> {
-        match d {
-            Jet::Left => {
-                if self
-                    .iter()
-                    .all(|(x, y)| *x > 0 && !bottom.contains(&(*x - 1, *y)))
-                {
-                    self.iter_mut().for_each(|(x, _)| {
-                        *x -= 1;
-                    });
-                    Ok(self)
-                } else {
-                    Err(self)
-                }
-            }
-            Jet::Right => {
-                if self
-                    .iter()
-                    .all(|(x, y)| *x + 1 < WIDTH && !bottom.contains(&(*x + 1, *y)))
-                {
-                    self.iter_mut().for_each(|(x, _)| {
-                        *x += 1;
-                    });
-                    Ok(self)
-                } else {
-                    Err(self)
-                }
-            }
+// one bit per column, the lowest one against the left wall
+type Row = u16;
+const _: () = assert!(
+    WIDTH <= Row::BITS as usize,
+    "the chamber is wider than a row"
+);
+const FULL: Row = Row::MAX >> (Row::BITS as usize - WIDTH);
+
+// whether the rows, from the given height up, don't overlap the tower
+fn fits(rows: impl Iterator<Item = Row>, y: usize, tower: &[Row]) -> bool {
+    rows.zip(y..)
+        .all(|(row, y)| row & tower.get(y).copied().unwrap_or_default() == 0)
+}
+
+// a falling rock, as its rows from the bottom up shifted to where it is
+#[derive(Clone, Debug)]
+struct Rock {
+    rows: Vec<Row>,
+    y: usize,
+}
+
+impl From<&Vec<(usize, usize)>> for Rock {
+    fn from(cells: &Vec<(usize, usize)>) -> Self {
+        let mut rows = vec![0; cells.iter().map(|(_, y)| y + 1).max().unwrap_or_default()];
+        for (x, y) in cells {
+            rows[*y] |= 1 << (x + START_LEFT);
         }
+        Self { rows, y: 0 }
     }
+}
 
-    fn down(mut self, other: &Self) -> Result<Self, Self> {
-        if self
-            .iter()
-            .all(|(x, y)| *y > 0 && !other.contains(&(*x, *y - 1)))
+impl Rock {
+    fn apply(mut self, d: Jet, tower: &Tower) -> Result<Self, Self> {
+        let (wall, shift): (Row, fn(Row) -> Row) = match d {
+            Jet::Left => (1, |row| row >> 1),
+            Jet::Right => (1 << (WIDTH - 1), |row| row << 1),
+        };
+        if self.rows.iter().all(|row| row & wall == 0)
+            && fits(self.rows.iter().map(|row| shift(*row)), self.y, &tower.rows)
         {
-            self.iter_mut().for_each(|(_, y)| {
-                *y -= 1;
-            });
+            self.rows.iter_mut().for_each(|row| *row = shift(*row));
             Ok(self)
         } else {
             Err(self)
         }
     }
 
-    fn merge_into(mut self, blocks: Vec<(usize, usize)>) -> Self {
-        for (x, y) in blocks {
-            self.push((x, y));
+    fn down(mut self, tower: &Tower) -> Result<Self, Self> {
+        if self.y > 0 && fits(self.rows.iter().copied(), self.y - 1, &tower.rows) {
+            self.y -= 1;
+            Ok(self)
+        } else {
+            Err(self)
+        }
+    }
+}
+
+// the settled rocks, from the floor up
+struct Tower {
+    rows: Vec<Row>,
+}
+
+impl Tower {
+    fn merge_into(mut self, rock: Rock) -> Self {
+        for (row, y) in rock.rows.into_iter().zip(rock.y..) {
+            if y >= self.rows.len() {
+                self.rows.resize(y + 1, 0);
+            }
+            self.rows[y] |= row;
         }
         self
     }
 
     fn max_height(&self) -> usize {
-        self.iter().map(|(_, y)| *y).max().unwrap_or_default()
-    }
-}
-
-#[allow(dead_code)]
-fn display(me: &Vec<(usize, usize)>, block: &Vec<(usize, usize)>, jet: Jet) {
-    print!("\x1B[2J\x1B[1;1H");
-    let mut displayed = 0;
-    for y in (0..=block.max_height().max(me.max_height())).rev().take(50) {
-        let output = (0..WIDTH)
-            .map(|x| {
-                if me.contains(&(x, y)) {
-                    displayed += 1;
-                    '#'
-                } else if block.contains(&(x, y)) {
-                    displayed += 1;
-                    '@'
-                } else {
-                    '.'
-                }
-            })
-            .collect::<String>();
-        println!("{}", output);
-        if displayed == me.len() + block.len() {
-            break;
-        }
+        self.rows.len() - 1
     }
-    println!("{:?} {}", block, jet);
+}
+
+// the chamber in the puzzle's format, from the top of the tower or of the falling rock down to at
+// most the given number of rows
+fn render(tower: &Tower, rock: Option<&Rock>, rows: usize) -> String {
+    let top = rock.map_or(tower.rows.len(), |rock| {
+        tower.rows.len().max(rock.y + rock.rows.len())
+    });
+    let mut output = String::new();
+    for y in (1..top).rev().take(rows) {
+        let settled = tower.rows.get(y).copied().unwrap_or_default();
+        let falling = rock
+            .and_then(|rock| y.checked_sub(rock.y).and_then(|i| rock.rows.get(i)))
+            .copied()
+            .unwrap_or_default();
+        output.push('|');
+        output.extend((0..WIDTH).map(|x| {
+            if settled & 1 << x != 0 {
+                '#'
+            } else if falling & 1 << x != 0 {
+                '@'
+            } else {
+                '.'
+            }
+        }));
+        output.push_str("|\n");
+    }
+    if top <= rows + 1 {
+        output.push_str(&format!("+{}+\n", "-".repeat(WIDTH)));
+    }
+    output
+}
+
+fn show(title: &str, tower: &Tower, rock: Option<&Rock>) {
+    println!("{}:", title);
+    println!("{}", render(tower, rock, RENDER_ROWS));
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let patterns: Vec<Jet> = {
         let lines = stdin().lock().lines();
-        let mut lines = lines.flatten();
+        let mut lines = lines.map_while(Result::ok);
         let patterns = lines
             .next()
             .ok_or("expected input")?
@@ -207,39 +293,69 @@ fn main() -> Result<(), Box<dyn Error>> {
         patterns
     };
 
-    let shapes = Repeater::from(
-        [
-            Shape::Horizontal,
-            Shape::Plus,
-            Shape::L,
-            Shape::Vertical,
-            Shape::Square,
-        ]
-        .as_slice(),
-    );
+    let drawings = match args().nth(1) {
+        Some(path) => fs::read_to_string(path)?,
+        None => ROCKS.to_string(),
+    };
+    let rocks = rocks(&drawings)?.iter().map(Rock::from).collect::<Vec<_>>();
+    let shapes = Repeater::from(rocks.as_slice());
 
     let mut jets = Repeater::from(patterns.as_slice());
 
-    let mut bottom = Vec::from_iter((0..WIDTH).map(|x| (x, 0usize)));
+    let mut bottom = Tower { rows: vec![FULL] };
 
-    for &shape in shapes.take(ITERATIONS) {
-        let mut block = Vec::from(shape);
-        let min_y = bottom.max_height() + START_HEIGHT + 1;
-        block.iter_mut().for_each(|(x, y)| {
-            *x += 2;
-            *y += min_y;
-        });
+    for (count, shape) in (1..).zip(shapes.take(ITERATIONS)) {
+        let mut block = shape.clone();
+        block.y = bottom.max_height() + START_HEIGHT + 1;
+
+        let watched = RENDER_FALL == Some(count);
+        if watched {
+            show(
+                &format!("Rock {} begins falling", count),
+                &bottom,
+                Some(&block),
+            );
+        }
 
         for &jet in &mut jets {
-            block = block.apply(jet, &bottom).any();
+            let pushed = block.apply(jet, &bottom);
+            if watched {
+                let direction = match jet {
+                    Jet::Left => "left",
+                    Jet::Right => "right",
+                };
+                let title = match &pushed {
+                    Ok(_) => format!("Jet of gas pushes rock {}", direction),
+                    Err(_) => format!("Jet of gas pushes rock {}, but nothing happens", direction),
+                };
+                let (Ok(block) | Err(block)) = &pushed;
+                show(&title, &bottom, Some(block));
+            }
+            block = pushed.any();
             block = match block.down(&bottom) {
-                Ok(block) => block,
+                Ok(block) => {
+                    if watched {
+                        show("Rock falls 1 unit", &bottom, Some(&block));
+                    }
+                    block
+                }
                 Err(block) => {
                     bottom = bottom.merge_into(block);
+                    if watched {
+                        show(
+                            "Rock falls 1 unit, causing it to come to rest",
+                            &bottom,
+                            None,
+                        );
+                    }
                     break;
                 }
             }
         }
+
+        if RENDER_AFTER == Some(count) {
+            show(&format!("After {} rocks", count), &bottom, None);
+        }
     }
 
     println!("{}", bottom.max_height());
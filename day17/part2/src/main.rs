@@ -1,9 +1,9 @@
 use std::collections::HashMap;
+use std::env::args;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
+use std::fs;
 use std::io::{stdin, BufRead};
-use std::ops::{Deref, DerefMut};
-use std::vec::IntoIter;
 
 #[derive(Copy, Clone)]
 enum Jet {
@@ -44,43 +44,80 @@ impl TryFrom<u8> for Jet {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-enum Shape {
-    Horizontal,
-    Plus,
-    L,
-    Vertical,
-    Square,
-}
+// the puzzle's rocks, in the format of a definition file: drawings separated by empty lines
+const ROCKS: &str = "\
+####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##
+";
+
+// cells of every rock of the drawings, from the bottom left of the rock
+fn rocks(drawings: &str) -> Result<Vec<Vec<(usize, usize)>>, String> {
+    let mut rocks = vec![];
+    let mut rows = vec![];
+    for line in drawings.lines().map(str::trim).chain([""]) {
+        if !line.is_empty() {
+            rows.push(line);
+            continue;
+        }
+        if rows.is_empty() {
+            continue;
+        }
 
-impl From<Shape> for Blocks {
-    fn from(shape: Shape) -> Self {
-        Self::from(match shape {
-            Shape::Horizontal => vec![(0, 0), (1, 0), (2, 0), (3, 0)],
-            Shape::Plus => vec![(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)],
-            Shape::L => vec![(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)],
-            Shape::Vertical => vec![(0, 0), (0, 1), (0, 2), (0, 3)],
-            Shape::Square => vec![(0, 0), (1, 0), (0, 1), (1, 1)],
-        })
-    }
-}
+        let mut rock = vec![];
+        for (y, row) in rows.drain(..).rev().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    '#' => rock.push((x, y)),
+                    '.' => {}
+                    _ => {
+                        return Err(format!(
+                            "invalid character {:?} in rock {}",
+                            c,
+                            rocks.len() + 1
+                        ))
+                    }
+                }
+            }
+        }
 
-impl From<Vec<(usize, usize)>> for Blocks {
-    fn from(vec: Vec<(usize, usize)>) -> Self {
-        Self {
-            blocks: vec,
-            base: 0,
+        // drawings may have empty rows or columns around the rock
+        let min_x = rock.iter().map(|(x, _)| *x).min();
+        let min_y = rock.iter().map(|(_, y)| *y).min();
+        let (Some(min_x), Some(min_y)) = (min_x, min_y) else {
+            return Err(format!("rock {} is empty", rocks.len() + 1));
+        };
+        rock.iter_mut().for_each(|(x, y)| {
+            *x -= min_x;
+            *y -= min_y;
+        });
+        if rock.iter().any(|(x, _)| START_LEFT + x >= WIDTH) {
+            return Err(format!(
+                "rock {} doesn't fit in the chamber",
+                rocks.len() + 1
+            ));
         }
+        rocks.push(rock);
     }
-}
-
-impl IntoIterator for Blocks {
-    type Item = (usize, usize);
-    type IntoIter = IntoIter<(usize, usize)>;
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.blocks.into_iter()
+    if rocks.is_empty() {
+        return Err("no rocks defined".into());
     }
+    Ok(rocks)
 }
 
 struct Repeater<'a, I>(&'a [I], usize);
@@ -102,8 +139,14 @@ impl<'a, I> From<&'a [I]> for Repeater<'a, I> {
 }
 
 const WIDTH: usize = 7;
-const ITERATIONS: usize = 1000000000000;
+// distance between the left wall and a new rock
+const START_LEFT: usize = 2;
+// numbers of rocks to tell the height of the tower after
+const QUERIES: &[usize] = &[2022, 1000000, 1000000000000];
+// distance between the highest rock and a new one
 const START_HEIGHT: usize = 3;
+// deepest rocks can still reach below the top, so that the tower can repeat itself
+const MAX_DEPTH: usize = 1000;
 
 trait AnyResult<I> {
     fn any(self) -> I;
@@ -118,130 +161,144 @@ impl<I> AnyResult<I> for Result<I, I> {
     }
 }
 
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
-struct Blocks {
-    blocks: Vec<(usize, usize)>,
-    base: usize,
-}
-
-impl Deref for Blocks {
-    type Target = Vec<(usize, usize)>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.blocks
-    }
+// one bit per column, the lowest one against the left wall
+type Row = u16;
+const _: () = assert!(
+    WIDTH <= Row::BITS as usize,
+    "the chamber is wider than a row"
+);
+const FULL: Row = Row::MAX >> (Row::BITS as usize - WIDTH);
+
+// whether the rows, from the given height up, don't overlap the tower
+fn fits(rows: impl Iterator<Item = Row>, y: usize, tower: &[Row]) -> bool {
+    rows.zip(y..)
+        .all(|(row, y)| row & tower.get(y).copied().unwrap_or_default() == 0)
 }
 
-impl Debug for Blocks {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        Debug::fmt(&self.blocks, f)
-    }
+// a falling rock, as its rows from the bottom up shifted to where it is
+#[derive(Clone, Debug)]
+struct Rock {
+    rows: Vec<Row>,
+    y: usize,
 }
 
-impl DerefMut for Blocks {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.blocks
+impl From<&Vec<(usize, usize)>> for Rock {
+    fn from(cells: &Vec<(usize, usize)>) -> Self {
+        let mut rows = vec![0; cells.iter().map(|(_, y)| y + 1).max().unwrap_or_default()];
+        for (x, y) in cells {
+            rows[*y] |= 1 << (x + START_LEFT);
+        }
+        Self { rows, y: 0 }
     }
 }
 
-impl Blocks {
-    fn apply(mut self, d: Jet, bottom: &Self) -> Result<Self, Self> {
-        match d {
-            Jet::Left => {
-                if self
-                    .iter()
-                    .all(|(x, y)| *x > 0 && !bottom.contains(&(*x - 1, *y)))
-                {
-                    self.iter_mut().for_each(|(x, _)| {
-                        *x -= 1;
-                    });
-                    Ok(self)
-                } else {
-                    Err(self)
-                }
-            }
-            Jet::Right => {
-                if self
-                    .iter()
-                    .all(|(x, y)| *x + 1 < WIDTH && !bottom.contains(&(*x + 1, *y)))
-                {
-                    self.iter_mut().for_each(|(x, _)| {
-                        *x += 1;
-                    });
-                    Ok(self)
-                } else {
-                    Err(self)
-                }
-            }
+impl Rock {
+    fn apply(mut self, d: Jet, tower: &Tower) -> Result<Self, Self> {
+        let (wall, shift): (Row, fn(Row) -> Row) = match d {
+            Jet::Left => (1, |row| row >> 1),
+            Jet::Right => (1 << (WIDTH - 1), |row| row << 1),
+        };
+        if self.rows.iter().all(|row| row & wall == 0)
+            && fits(self.rows.iter().map(|row| shift(*row)), self.y, &tower.rows)
+        {
+            self.rows.iter_mut().for_each(|row| *row = shift(*row));
+            Ok(self)
+        } else {
+            Err(self)
         }
     }
 
-    fn down(mut self, other: &Self) -> Result<Self, Self> {
-        if self
-            .iter()
-            .all(|(x, y)| *y > 0 && !other.contains(&(*x, *y - 1)))
-        {
-            self.iter_mut().for_each(|(_, y)| {
-                *y -= 1;
-            });
+    fn down(mut self, tower: &Tower) -> Result<Self, Self> {
+        if self.y > 0 && fits(self.rows.iter().copied(), self.y - 1, &tower.rows) {
+            self.y -= 1;
             Ok(self)
         } else {
             Err(self)
         }
     }
+}
 
-    fn merge_into(mut self, blocks: Blocks) -> (Self, Option<Self>) {
-        let mut remove_at = None;
-
-        for (x, y) in blocks {
-            self.push((x, y));
+// the settled rocks that can still be reached, from `base` up
+struct Tower {
+    rows: Vec<Row>,
+    base: usize,
+}
 
-            if y > 1 && remove_at.is_none() {
-                let mut found = [false; WIDTH];
-                self.iter()
-                    .filter(|(_, sy)| *sy == y || *sy + 1 == y)
-                    .for_each(|(x, _)| {
-                        found[*x] = true;
-                    });
+impl Tower {
+    // adds the rock to the tower and forgets the rows no rock can reach anymore
+    fn merge_into(mut self, rock: Rock) -> Result<Self, String> {
+        for (row, y) in rock.rows.into_iter().zip(rock.y..) {
+            if y >= self.rows.len() {
+                self.rows.resize(y + 1, 0);
+            }
+            self.rows[y] |= row;
+        }
 
-                if found.into_iter().all(|b| b) {
-                    remove_at = Some(y);
+        // rocks only move sideways and down, so they can't go anywhere the air above can't
+        let mut reached = FULL;
+        let mut lowest = self.rows.len();
+        for y in (0..self.rows.len()).rev() {
+            let free = !self.rows[y] & FULL;
+            reached &= free;
+            loop {
+                let spread = (reached | reached << 1 | reached >> 1) & free;
+                if spread == reached {
+                    break;
                 }
+                reached = spread;
             }
+            if reached == 0 {
+                break;
+            }
+            lowest = y;
         }
 
-        if let Some(remove_at) = remove_at {
-            let mut clone = self.clone();
-            clone.base = 0;
-
-            self.base = self.base + remove_at - 1;
-            self.retain_mut(|(_, sy)| {
-                if *sy >= remove_at - 1 {
-                    *sy -= remove_at - 1;
-                    true
-                } else {
-                    false
-                }
-            });
-            return (self, Some(clone));
+        if self.rows.len() - lowest > MAX_DEPTH {
+            return Err(format!(
+                "rocks can still reach {} rows below the top, more than {}",
+                self.rows.len() - lowest,
+                MAX_DEPTH
+            ));
         }
-        (self, None)
+
+        // the row below the lowest reachable one still stops rocks
+        if lowest > 1 {
+            self.base += lowest - 1;
+            self.rows.drain(..lowest - 1);
+        }
+        Ok(self)
     }
 
     fn max_height(&self) -> usize {
-        self.iter().map(|(_, y)| *y).max().unwrap_or_default() + self.base
+        self.rows.len() - 1 + self.base
     }
+}
+
+// the tower repeats itself every `length` rocks once `start` of them fell, each time growing by
+// `height`, and the first rock of the cycle has the given shape and first jet
+struct Cycle {
+    start: usize,
+    length: usize,
+    height: usize,
+    shape: usize,
+    jet: usize,
+}
 
-    #[allow(dead_code)]
-    fn min_height(&self) -> usize {
-        self.iter().map(|(_, y)| *y).min().unwrap_or_default() + self.base
+impl Cycle {
+    // height after the given number of rocks, from the heights until the cycle was found
+    fn height(&self, heights: &[usize], count: usize) -> usize {
+        if count < heights.len() {
+            return heights[count];
+        }
+        let periods = (count - self.start) / self.length;
+        heights[self.start + (count - self.start) % self.length] + periods * self.height
     }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let patterns: Vec<Jet> = {
         let lines = stdin().lock().lines();
-        let mut lines = lines.flatten();
+        let mut lines = lines.map_while(Result::ok);
         let patterns = lines
             .next()
             .ok_or("expected input")?
@@ -254,63 +311,83 @@ fn main() -> Result<(), Box<dyn Error>> {
         patterns
     };
 
-    let shapes = Repeater::from(
-        [
-            Shape::Horizontal,
-            Shape::Plus,
-            Shape::L,
-            Shape::Vertical,
-            Shape::Square,
-        ]
-        .as_slice(),
-    );
+    let drawings = match args().nth(1) {
+        Some(path) => fs::read_to_string(path)?,
+        None => ROCKS.to_string(),
+    };
+    let rocks = rocks(&drawings)?.iter().map(Rock::from).collect::<Vec<_>>();
+    let shapes = Repeater::from(rocks.as_slice());
 
     let mut jets = Repeater::from(patterns.as_slice());
 
-    let mut bottom: Blocks = Vec::from_iter((0..WIDTH).map(|x| (x, 0usize))).into();
+    let mut bottom = Tower {
+        rows: vec![FULL],
+        base: 0,
+    };
+    // height after every number of rocks, until the tower starts repeating
+    let mut heights = vec![0];
     let mut clones = HashMap::new();
-    let mut iteration = 0;
+    let mut cycle = None;
+    let last = QUERIES.iter().copied().max().unwrap_or_default();
 
-    for (shape_index, &shape) in shapes.take(ITERATIONS) {
-        iteration += 1;
-        if iteration > ITERATIONS {
+    'rocks: for (shape_index, shape) in shapes {
+        if heights.len() > last {
             break;
         }
-        let mut block = Blocks::from(shape);
+        let mut block = shape.clone();
 
-        let min_y = bottom.max_height() + START_HEIGHT + 1 - bottom.base;
-        block.iter_mut().for_each(|(x, y)| {
-            *x += 2;
-            *y += min_y;
-        });
+        block.y = bottom.max_height() + START_HEIGHT + 1 - bottom.base;
 
         for (jet_index, &jet) in &mut jets {
             block = block.apply(jet, &bottom).any();
             block = match block.down(&bottom) {
                 Ok(block) => block,
                 Err(block) => {
-                    let (mut new_bottom, clone) = bottom.merge_into(block);
-                    if let Some(clone) = clone {
-                        if let Some((previous_iteration, previous_height)) = clones.insert(
-                            (clone, jet_index, shape_index),
-                            (iteration, new_bottom.max_height()),
-                        ) {
-                            let loop_length = iteration - previous_iteration;
-                            let loop_height = new_bottom.max_height() - previous_height;
-                            let repeat = (ITERATIONS - iteration) / loop_length;
-                            new_bottom.base += loop_height * repeat;
-                            iteration += repeat * loop_length;
-                        };
+                    bottom = bottom.merge_into(block)?;
+                    heights.push(bottom.max_height());
+                    let iteration = heights.len() - 1;
+                    if let Some(start) =
+                        clones.insert((bottom.rows.clone(), jet_index, shape_index), iteration)
+                    {
+                        cycle = Some(Cycle {
+                            start,
+                            length: iteration - start,
+                            height: heights[iteration] - heights[start],
+                            shape: (shape_index + 1) % rocks.len(),
+                            jet: (jet_index + 1) % patterns.len(),
+                        });
+                        break 'rocks;
                     }
-
-                    bottom = new_bottom;
                     break;
                 }
             }
         }
     }
 
-    println!("{}", bottom.max_height());
+    for &count in QUERIES {
+        let height = match &cycle {
+            Some(cycle) => cycle.height(&heights, count),
+            None => heights[count],
+        };
+        println!("{}: {}", count, height);
+    }
+    println!();
+
+    match cycle {
+        Some(cycle) => {
+            println!(
+                "cycle from rock {} (shape {}, jet {})",
+                cycle.start + 1,
+                cycle.shape,
+                cycle.jet
+            );
+            println!(
+                "{} rocks per period, {} rows higher each",
+                cycle.length, cycle.height
+            );
+        }
+        None => println!("no cycle within {} rocks", last),
+    }
 
     Ok(())
 }
@@ -1,6 +1,11 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::env::args;
 use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
 use std::io::{stdin, BufRead};
 use std::iter::once_with;
+use std::path::Path;
 
 fn rotations<T>(cubes: &'_ T) -> impl Iterator<Item = T> + '_
 where
@@ -33,11 +38,171 @@ fn touches(left: [u8; 3], right: [u8; 3]) -> bool {
     })
 }
 
+// side of a unit cube, perpendicular to the axis and facing its positive or negative direction
+#[derive(Copy, Clone, Debug)]
+struct Face {
+    cube: [i32; 3],
+    axis: usize,
+    positive: bool,
+}
+
+// rectangle of the mesh, its corners counterclockwise when seen from where its normal points
+struct Quad {
+    normal: [i32; 3],
+    corners: [[i32; 3]; 4],
+}
+
+impl Quad {
+    // `start` and `size` are along the two other axes, in the order following `axis`
+    fn new(axis: usize, positive: bool, level: i32, start: (i32, i32), size: (i32, i32)) -> Self {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let corner = |du, dv| {
+            let mut corner = [0; 3];
+            corner[axis] = level + positive as i32;
+            corner[u] = start.0 + du;
+            corner[v] = start.1 + dv;
+            corner
+        };
+        let mut corners = [
+            corner(0, 0),
+            corner(size.0, 0),
+            corner(size.0, size.1),
+            corner(0, size.1),
+        ];
+        if !positive {
+            corners.reverse();
+        }
+        let mut normal = [0; 3];
+        normal[axis] = if positive { 1 } else { -1 };
+        Self { normal, corners }
+    }
+}
+
+// one quad per face, or when merged the faces of every plane greedily joined into rectangles
+fn quads(faces: &[Face], merged: bool) -> Vec<Quad> {
+    let mut planes = BTreeMap::<_, BTreeSet<_>>::new();
+    for face in faces {
+        let (u, v) = ((face.axis + 1) % 3, (face.axis + 2) % 3);
+        planes
+            .entry((face.axis, face.positive, face.cube[face.axis]))
+            .or_default()
+            .insert((face.cube[u], face.cube[v]));
+    }
+
+    let mut quads = vec![];
+    for ((axis, positive, level), mut cells) in planes {
+        while let Some((u, v)) = cells.pop_first() {
+            let mut size = (1, 1);
+            if merged {
+                while cells.remove(&(u, v + size.1)) {
+                    size.1 += 1;
+                }
+                while (0..size.1).all(|dv| cells.contains(&(u + size.0, v + dv))) {
+                    for dv in 0..size.1 {
+                        cells.remove(&(u + size.0, v + dv));
+                    }
+                    size.0 += 1;
+                }
+            }
+            quads.push(Quad::new(axis, positive, level, (u, v), size));
+        }
+    }
+    quads
+}
+
+// wavefront obj with the corners shared between quads
+fn obj(quads: &[Quad]) -> String {
+    let mut vertices = HashMap::new();
+    let mut output = String::new();
+    let mut faces = String::new();
+    let mut normals = vec![];
+    for quad in quads {
+        let normal = match normals.iter().position(|normal| *normal == quad.normal) {
+            Some(index) => index,
+            None => {
+                let [x, y, z] = quad.normal;
+                output.push_str(&format!("vn {} {} {}\n", x, y, z));
+                normals.push(quad.normal);
+                normals.len() - 1
+            }
+        };
+        faces.push('f');
+        for corner in quad.corners {
+            let next = vertices.len() + 1;
+            let index = *vertices.entry(corner).or_insert_with(|| {
+                let [x, y, z] = corner;
+                output.push_str(&format!("v {} {} {}\n", x, y, z));
+                next
+            });
+            faces.push_str(&format!(" {}//{}", index, normal + 1));
+        }
+        faces.push('\n');
+    }
+    output + &faces
+}
+
+// ascii stl, two triangles per quad
+fn stl(quads: &[Quad]) -> String {
+    let mut output = "solid droplet\n".to_string();
+    for quad in quads {
+        for triangle in [[0, 1, 2], [0, 2, 3]] {
+            let [x, y, z] = quad.normal;
+            output.push_str(&format!("  facet normal {} {} {}\n", x, y, z));
+            output.push_str("    outer loop\n");
+            for corner in triangle {
+                let [x, y, z] = quad.corners[corner];
+                output.push_str(&format!("      vertex {} {} {}\n", x, y, z));
+            }
+            output.push_str("    endloop\n");
+            output.push_str("  endfacet\n");
+        }
+    }
+    output + "endsolid droplet\n"
+}
+
+// the faces in the format given by the extension of the path
+fn mesh(path: &str, faces: &[Face]) -> Result<String, String> {
+    let quads = quads(faces, MESH_MERGED);
+    match Path::new(path).extension().and_then(OsStr::to_str) {
+        Some("obj") => Ok(obj(&quads)),
+        Some("stl") => Ok(stl(&quads)),
+        _ => Err("expected a path to an .obj or .stl file".into()),
+    }
+}
+
+// join the faces of a plane into as few rectangles as the greedy merge finds
+const MESH_MERGED: bool = false;
+
+// faces of the cubes not covered by another cube
+fn faces(cubes: &[[u8; 3]]) -> Vec<Face> {
+    let cubes = cubes
+        .iter()
+        .map(|cube| cube.map(i32::from))
+        .collect::<HashSet<_>>();
+    let mut faces = vec![];
+    for &cube in &cubes {
+        for axis in 0..3 {
+            for positive in [false, true] {
+                let mut neighbour = cube;
+                neighbour[axis] += if positive { 1 } else { -1 };
+                if !cubes.contains(&neighbour) {
+                    faces.push(Face {
+                        cube,
+                        axis,
+                        positive,
+                    });
+                }
+            }
+        }
+    }
+    faces
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     let cubes: Vec<[u8; 3]> = {
         let lines = stdin().lock().lines();
         lines
-            .flatten()
+            .map_while(Result::ok)
             .map(|line| {
                 line.split(',')
                     .map(str::parse)
@@ -54,8 +219,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     };
 
     let groups = cubes
-        .into_iter()
-        .fold(vec![], |mut acc: Vec<(Vec<[u8; 3]>, usize)>, cube| {
+        .iter()
+        .fold(vec![], |mut acc: Vec<(Vec<[u8; 3]>, usize)>, &cube| {
             let mut merge_with = acc.iter_mut().filter_map(|(group, touching)| {
                 let count = group
                     .iter()
@@ -85,5 +250,13 @@ fn main() -> Result<(), Box<dyn Error>> {
         .sum::<usize>();
     println!("{}", result);
 
+    if let Some(path) = args().nth(1) {
+        let faces = faces(&cubes);
+        if faces.len() != result {
+            return Err(format!("{} faces for a surface of {}", faces.len(), result).into());
+        }
+        fs::write(&path, mesh(&path, &faces)?)?;
+    }
+
     Ok(())
 }
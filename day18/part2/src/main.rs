@@ -1,146 +1,564 @@
 use std::cmp::Reverse;
-use std::collections::hash_map::RandomState;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::env::args;
 use std::error::Error;
-use std::io::{stdin, BufRead};
-
-fn edge_neighbours(cube: [i8; 3]) -> [[i8; 3]; 14] {
-    [
-        [cube[0] - 1, cube[1], cube[2]],
-        [cube[0] - 1, cube[1] - 1, cube[2]],
-        [cube[0] - 1, cube[1] + 1, cube[2]],
-        [cube[0] - 1, cube[1], cube[2] - 1],
-        [cube[0] - 1, cube[1], cube[2] + 1],
-        [cube[0] + 1, cube[1], cube[2]],
-        [cube[0] + 1, cube[1] - 1, cube[2]],
-        [cube[0] + 1, cube[1] + 1, cube[2]],
-        [cube[0] + 1, cube[1], cube[2] - 1],
-        [cube[0] + 1, cube[1], cube[2] + 1],
-        [cube[0], cube[1] - 1, cube[2]],
-        [cube[0], cube[1] + 1, cube[2]],
-        [cube[0], cube[1], cube[2] - 1],
-        [cube[0], cube[1], cube[2] + 1],
-    ]
-}
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{stdin, Read};
+use std::path::Path;
 
-fn neighbours(cube: [i8; 3]) -> [[i8; 3]; 6] {
-    [
-        [cube[0] - 1, cube[1], cube[2]],
-        [cube[0] + 1, cube[1], cube[2]],
-        [cube[0], cube[1] - 1, cube[2]],
-        [cube[0], cube[1] + 1, cube[2]],
-        [cube[0], cube[1], cube[2] - 1],
-        [cube[0], cube[1], cube[2] + 1],
-    ]
+// the cubes in a dense grid, with one empty cell of padding on every side so that the air around
+// the droplet is connected
+struct Grid {
+    min: [i32; 3],
+    size: [usize; 3],
+    cubes: Vec<bool>,
 }
 
-fn exposed(cubes: Vec<[i8; 3]>, trapped: &mut HashSet<[i8; 3]>) -> usize {
-    let Some(mut first) = cubes.iter().max().copied() else {
-        return 0
-    };
-    first[0] += 1;
-    let cubes = HashSet::<_, RandomState>::from_iter(cubes);
-    let mut air = HashSet::new();
-
-    let mut queue = vec![(first, true)];
-    let mut exposed = 0;
-    while let Some((location, should_dive)) = queue.pop() {
-        if air.contains(&location) {
-            continue;
+impl Grid {
+    fn new(cubes: &[[i32; 3]]) -> Result<Self, String> {
+        let mut min = [0; 3];
+        let mut size = [0; 3];
+        for axis in 0..3 {
+            let values = cubes.iter().map(|cube| cube[axis]);
+            let (Some(low), Some(high)) = (values.clone().min(), values.max()) else {
+                return Ok(Self {
+                    min,
+                    size,
+                    cubes: vec![],
+                });
+            };
+            min[axis] = low - 1;
+            size[axis] = (high.abs_diff(low) as usize) + 3;
         }
 
-        if cubes.contains(&location) {
-            exposed += 1;
-            continue;
+        let cells = size
+            .iter()
+            .try_fold(1usize, |cells, size| cells.checked_mul(*size))
+            .filter(|cells| *cells <= MAX_CELLS)
+            .ok_or("droplet too large for a dense grid")?;
+        let mut grid = Self {
+            min,
+            size,
+            cubes: vec![false; cells],
+        };
+        for cube in cubes {
+            let index = grid.index(grid.cell(*cube));
+            grid.cubes[index] = true;
         }
-        air.insert(location);
+        Ok(grid)
+    }
 
-        let neighbours = neighbours(location);
-        let touches = edge_neighbours(location)
-            .iter()
-            .any(|cube| cubes.contains(cube));
-        if !touches && !should_dive {
-            continue;
+    // position of the cube in the grid
+    fn cell(&self, cube: [i32; 3]) -> [usize; 3] {
+        [0, 1, 2].map(|axis| cube[axis].abs_diff(self.min[axis]) as usize)
+    }
+
+    fn index(&self, cell: [usize; 3]) -> usize {
+        (cell[2] * self.size[1] + cell[1]) * self.size[0] + cell[0]
+    }
+
+    // cells sharing a face with the given one within the grid, with the axis they are along and
+    // whether they are in its positive direction
+    fn neighbours(&self, cell: [usize; 3]) -> impl Iterator<Item = ([usize; 3], usize, bool)> + '_ {
+        (0..3).flat_map(move |axis| {
+            let mut below = cell;
+            let mut above = cell;
+            below[axis] = below[axis].wrapping_sub(1);
+            above[axis] += 1;
+            [(below, axis, false), (above, axis, true)]
+                .into_iter()
+                .filter(move |(neighbour, _, _)| neighbour[axis] < self.size[axis])
+        })
+    }
+
+    fn position(&self, index: usize) -> [usize; 3] {
+        [
+            index % self.size[0],
+            index / self.size[0] % self.size[1],
+            index / self.size[0] / self.size[1],
+        ]
+    }
+
+    fn world(&self, cell: [usize; 3]) -> [i32; 3] {
+        [0, 1, 2].map(|axis| self.min[axis] + cell[axis] as i32)
+    }
+
+    // faces of the cubes not covered by another cube
+    fn surface(&self) -> usize {
+        (0..self.cubes.len())
+            .filter(|index| self.cubes[*index])
+            .map(|index| {
+                self.neighbours(self.position(index))
+                    .filter(|(neighbour, _, _)| !self.cubes[self.index(*neighbour)])
+                    .count()
+            })
+            .sum()
+    }
+
+    // the air connected to the given cell, marking it as visited
+    fn flood(&self, start: [usize; 3], visited: &mut [bool]) -> Air {
+        let mut air = Air {
+            volume: 1,
+            min: self.world(start),
+            max: self.world(start),
+            faces: vec![],
+        };
+        visited[self.index(start)] = true;
+        let mut queue = vec![start];
+        while let Some(cell) = queue.pop() {
+            for (neighbour, axis, positive) in self.neighbours(cell) {
+                let index = self.index(neighbour);
+                if self.cubes[index] {
+                    air.faces.push(Face {
+                        cube: self.world(neighbour),
+                        axis,
+                        positive: !positive,
+                    });
+                } else if !visited[index] {
+                    visited[index] = true;
+                    let world = self.world(neighbour);
+                    air.volume += 1;
+                    air.min = [0, 1, 2].map(|axis| air.min[axis].min(world[axis]));
+                    air.max = [0, 1, 2].map(|axis| air.max[axis].max(world[axis]));
+                    queue.push(neighbour);
+                }
+            }
         }
-        queue.extend(neighbours.into_iter().map(|cube| (cube, touches)));
+        air
     }
 
-    let mut trapped_queue = cubes
-        .iter()
-        .flat_map(|cube| neighbours(*cube))
-        .collect::<Vec<_>>();
+    // the air outside of the droplet, flooded from a corner of the padding, and the pockets of air
+    // enclosed in it, largest first
+    fn air(&self) -> Option<(Air, Vec<Air>)> {
+        if self.cubes.is_empty() {
+            return None;
+        }
 
-    while let Some(location) = trapped_queue.pop() {
-        if cubes.contains(&location) || trapped.contains(&location) || air.contains(&location) {
-            continue;
+        let mut visited = vec![false; self.cubes.len()];
+        let outside = self.flood([0; 3], &mut visited);
+        let mut pockets = vec![];
+        for index in 0..self.cubes.len() {
+            if !self.cubes[index] && !visited[index] {
+                pockets.push(self.flood(self.position(index), &mut visited));
+            }
         }
-        trapped.insert(location);
-        trapped_queue.extend(neighbours(location))
+        pockets.sort_by_key(|pocket| Reverse(pocket.volume));
+        Some((outside, pockets))
     }
+}
 
-    exposed
+// connected cells of air, with the faces of the cubes they touch
+struct Air {
+    volume: usize,
+    min: [i32; 3],
+    max: [i32; 3],
+    faces: Vec<Face>,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let cubes: Vec<[i8; 3]> = {
-        let lines = stdin().lock().lines();
-        lines
-            .flatten()
-            .map(|line| {
-                line.split(',')
-                    .map(str::parse)
-                    .enumerate()
-                    .try_fold([0; 3], |mut acc, (i, v)| {
-                        if i > 2 {
-                            return Err("trailing content".into());
-                        }
-                        acc[i] = v?;
-                        Ok(acc)
-                    })
-            })
-            .collect::<Result<_, Box<dyn Error>>>()?
-    };
+// side of a unit cube, perpendicular to the axis and facing its positive or negative direction
+#[derive(Copy, Clone, Debug)]
+struct Face {
+    cube: [i32; 3],
+    axis: usize,
+    positive: bool,
+}
+
+// rectangle of the mesh, its corners counterclockwise when seen from where its normal points
+struct Quad {
+    normal: [i32; 3],
+    corners: [[i32; 3]; 4],
+}
 
-    let mut groups = cubes
-        .into_iter()
-        .fold(vec![], |mut acc: Vec<Vec<[i8; 3]>>, cube| {
-            let edge_neighbours = edge_neighbours(cube);
+impl Quad {
+    // `start` and `size` are along the two other axes, in the order following `axis`
+    fn new(axis: usize, positive: bool, level: i32, start: (i32, i32), size: (i32, i32)) -> Self {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let corner = |du, dv| {
+            let mut corner = [0; 3];
+            corner[axis] = level + positive as i32;
+            corner[u] = start.0 + du;
+            corner[v] = start.1 + dv;
+            corner
+        };
+        let mut corners = [
+            corner(0, 0),
+            corner(size.0, 0),
+            corner(size.0, size.1),
+            corner(0, size.1),
+        ];
+        if !positive {
+            corners.reverse();
+        }
+        let mut normal = [0; 3];
+        normal[axis] = if positive { 1 } else { -1 };
+        Self { normal, corners }
+    }
+}
 
-            let mut merge_with = acc.iter_mut().filter_map(|group| {
-                group
-                    .iter()
-                    .any(|group_cube| edge_neighbours.contains(group_cube))
-                    .then_some(group)
+// one quad per face, or when merged the faces of every plane greedily joined into rectangles
+fn quads(faces: &[Face], merged: bool) -> Vec<Quad> {
+    let mut planes = BTreeMap::<_, BTreeSet<_>>::new();
+    for face in faces {
+        let (u, v) = ((face.axis + 1) % 3, (face.axis + 2) % 3);
+        planes
+            .entry((face.axis, face.positive, face.cube[face.axis]))
+            .or_default()
+            .insert((face.cube[u], face.cube[v]));
+    }
+
+    let mut quads = vec![];
+    for ((axis, positive, level), mut cells) in planes {
+        while let Some((u, v)) = cells.pop_first() {
+            let mut size = (1, 1);
+            if merged {
+                while cells.remove(&(u, v + size.1)) {
+                    size.1 += 1;
+                }
+                while (0..size.1).all(|dv| cells.contains(&(u + size.0, v + dv))) {
+                    for dv in 0..size.1 {
+                        cells.remove(&(u + size.0, v + dv));
+                    }
+                    size.0 += 1;
+                }
+            }
+            quads.push(Quad::new(axis, positive, level, (u, v), size));
+        }
+    }
+    quads
+}
+
+// wavefront obj with the corners shared between quads
+fn obj(quads: &[Quad]) -> String {
+    let mut vertices = HashMap::new();
+    let mut output = String::new();
+    let mut faces = String::new();
+    let mut normals = vec![];
+    for quad in quads {
+        let normal = match normals.iter().position(|normal| *normal == quad.normal) {
+            Some(index) => index,
+            None => {
+                let [x, y, z] = quad.normal;
+                output.push_str(&format!("vn {} {} {}\n", x, y, z));
+                normals.push(quad.normal);
+                normals.len() - 1
+            }
+        };
+        faces.push('f');
+        for corner in quad.corners {
+            let next = vertices.len() + 1;
+            let index = *vertices.entry(corner).or_insert_with(|| {
+                let [x, y, z] = corner;
+                output.push_str(&format!("v {} {} {}\n", x, y, z));
+                next
             });
+            faces.push_str(&format!(" {}//{}", index, normal + 1));
+        }
+        faces.push('\n');
+    }
+    output + &faces
+}
+
+// ascii stl, two triangles per quad
+fn stl(quads: &[Quad]) -> String {
+    let mut output = "solid droplet\n".to_string();
+    for quad in quads {
+        for triangle in [[0, 1, 2], [0, 2, 3]] {
+            let [x, y, z] = quad.normal;
+            output.push_str(&format!("  facet normal {} {} {}\n", x, y, z));
+            output.push_str("    outer loop\n");
+            for corner in triangle {
+                let [x, y, z] = quad.corners[corner];
+                output.push_str(&format!("      vertex {} {} {}\n", x, y, z));
+            }
+            output.push_str("    endloop\n");
+            output.push_str("  endfacet\n");
+        }
+    }
+    output + "endsolid droplet\n"
+}
+
+// the faces in the format given by the extension of the path
+fn mesh(path: &str, faces: &[Face]) -> Result<String, String> {
+    let quads = quads(faces, MESH_MERGED);
+    match Path::new(path).extension().and_then(OsStr::to_str) {
+        Some("obj") => Ok(obj(&quads)),
+        Some("stl") => Ok(stl(&quads)),
+        _ => Err("expected a path to an .obj or .stl file".into()),
+    }
+}
 
-            if let Some(first) = merge_with.next() {
-                first.push(cube);
+// join the faces of a plane into as few rectangles as the greedy merge finds
+const MESH_MERGED: bool = false;
+// pockets listed, the largest ones first
+const LIST_LIMIT: usize = 16;
+// the grid and the flood fill take a byte per cell each
+const MAX_CELLS: usize = 1 << 28;
 
-                for merger in merge_with {
-                    first.append(merger);
+// little endian reader over the bytes of a binary file
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if count > self.0.len() {
+            return Err("unexpected end of file".into());
+        }
+        let (taken, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.take(length)?).into_owned())
+    }
+
+    fn dict(&mut self) -> Result<HashMap<String, String>, String> {
+        (0..self.u32()?)
+            .map(|_| Ok((self.string()?, self.string()?)))
+            .collect()
+    }
+
+    // id of a magicavoxel chunk, its content and its children
+    fn chunk(&mut self) -> Result<(&'a [u8], Reader<'a>, Reader<'a>), String> {
+        let id = self.take(4)?;
+        let content = self.u32()? as usize;
+        let children = self.u32()? as usize;
+        Ok((
+            id,
+            Reader(self.take(content)?),
+            Reader(self.take(children)?),
+        ))
+    }
+}
+
+// voxels of a magicavoxel file, with its models placed where the scene translates them
+fn vox(bytes: &[u8]) -> Result<Vec<[i32; 3]>, String> {
+    let mut reader = Reader(bytes);
+    reader.take(8)?;
+    let (id, _, mut chunks) = reader.chunk()?;
+    if id != b"MAIN" {
+        return Err("expected a MAIN chunk".into());
+    }
+
+    let mut size = None;
+    let mut models = vec![];
+    let mut transforms = HashMap::new();
+    let mut groups = HashMap::new();
+    let mut shapes = HashMap::new();
+    while !chunks.0.is_empty() {
+        let (id, mut content, _) = chunks.chunk()?;
+        match id {
+            b"SIZE" => size = Some([content.u32()?, content.u32()?, content.u32()?]),
+            b"XYZI" => {
+                let size = size.take().ok_or("voxels without a size")?;
+                let voxels = (0..content.u32()?)
+                    .map(|_| {
+                        let voxel = content.take(4)?;
+                        Ok([voxel[0], voxel[1], voxel[2]].map(i32::from))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                models.push((size, voxels));
+            }
+            b"nTRN" => {
+                let node = content.u32()?;
+                content.dict()?;
+                let child = content.u32()?;
+                content.take(8)?;
+                let frame = match content.u32()? {
+                    0 => HashMap::new(),
+                    _ => content.dict()?,
+                };
+                if frame.contains_key("_r") {
+                    return Err("rotated models are not supported".into());
                 }
+                let translation = match frame.get("_t") {
+                    Some(translation) => {
+                        let mut values = translation.split(' ').map(str::parse);
+                        match (values.next(), values.next(), values.next()) {
+                            (Some(Ok(x)), Some(Ok(y)), Some(Ok(z))) => [x, y, z],
+                            _ => return Err(format!("invalid translation {:?}", translation)),
+                        }
+                    }
+                    None => [0; 3],
+                };
+                transforms.insert(node, (child, translation));
+            }
+            b"nGRP" => {
+                let node = content.u32()?;
+                content.dict()?;
+                let children = (0..content.u32()?)
+                    .map(|_| content.u32())
+                    .collect::<Result<Vec<_>, _>>()?;
+                groups.insert(node, children);
+            }
+            b"nSHP" => {
+                let node = content.u32()?;
+                content.dict()?;
+                let models = (0..content.u32()?)
+                    .map(|_| {
+                        let model = content.u32()?;
+                        content.dict()?;
+                        Ok(model as usize)
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                shapes.insert(node, models);
+            }
+            _ => {}
+        }
+    }
+
+    // files without a scene only have one model
+    if transforms.is_empty() {
+        return Ok(models.into_iter().flat_map(|(_, voxels)| voxels).collect());
+    }
+
+    // models are centered on their translation
+    let mut cubes = vec![];
+    let mut seen = HashSet::new();
+    let mut queue = vec![(0, [0; 3])];
+    while let Some((node, offset)) = queue.pop() {
+        if !seen.insert(node) {
+            return Err("nodes of the scene form a cycle".into());
+        }
+        if let Some((child, translation)) = transforms.get(&node) {
+            queue.push((
+                *child,
+                [0, 1, 2].map(|axis| offset[axis] + translation[axis]),
+            ));
+        } else if let Some(children) = groups.get(&node) {
+            queue.extend(children.iter().map(|child| (*child, offset)));
+        } else if let Some(ids) = shapes.get(&node) {
+            for id in ids {
+                let (size, voxels) = models.get(*id).ok_or("unknown model")?;
+                cubes.extend(voxels.iter().map(|voxel| {
+                    [0, 1, 2].map(|axis| offset[axis] + voxel[axis] - (size[axis] / 2) as i32)
+                }));
+            }
+        }
+    }
+    Ok(cubes)
+}
 
-                acc.retain(|cubes| !cubes.is_empty());
-            } else {
-                acc.push(vec![cube]);
+// voxels of a binvox file, which are run length encoded with y changing the fastest, then z
+fn binvox(bytes: &[u8]) -> Result<Vec<[i32; 3]>, String> {
+    let mut dimensions = None;
+    let mut rest = bytes;
+    loop {
+        let end = rest
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .ok_or("expected data after the header")?;
+        let line = String::from_utf8_lossy(&rest[..end]);
+        rest = &rest[end + 1..];
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("dim") => {
+                let values = words
+                    .map(str::parse)
+                    .collect::<Result<Vec<usize>, _>>()
+                    .map_err(|e| e.to_string())?;
+                dimensions =
+                    Some(<[usize; 3]>::try_from(values).map_err(|_| "expected three dimensions")?);
             }
-            acc
-        });
-
-    groups.sort_by_key(|g| Reverse(g.len()));
-    let mut trapped = HashSet::new();
-
-    let result = groups
-        .into_iter()
-        .map(|cubes| {
-            if cubes.iter().any(|cube| trapped.contains(cube)) {
-                0
-            } else {
-                exposed(cubes, &mut trapped)
+            Some("data") => break,
+            _ => {}
+        }
+    }
+    let [dx, dz, dy] = dimensions.ok_or("missing dimensions")?;
+
+    let mut cubes = vec![];
+    let mut index = 0;
+    for run in rest.chunks(2) {
+        let [value, count] = run else {
+            return Err("truncated run".into());
+        };
+        let count = *count as usize;
+        if *value != 0 {
+            for index in index..index + count {
+                let (x, z, y) = (index / dy / dz, index / dy % dz, index % dy);
+                cubes.push([x, y, z].map(|v| v as i32));
             }
+        }
+        index += count;
+    }
+    if index != dx * dy * dz {
+        return Err(format!("{} voxels for a {}x{}x{} grid", index, dx, dz, dy));
+    }
+    Ok(cubes)
+}
+
+// cubes as `x,y,z` lines, or from a magicavoxel or binvox file
+fn cubes(input: &[u8]) -> Result<Vec<[i32; 3]>, Box<dyn Error>> {
+    if input.starts_with(b"VOX ") {
+        return Ok(vox(input)?);
+    }
+    if input.starts_with(b"#binvox") {
+        return Ok(binvox(input)?);
+    }
+
+    std::str::from_utf8(input)?
+        .lines()
+        .map(|line| {
+            line.split(',')
+                .map(str::parse)
+                .enumerate()
+                .try_fold([0; 3], |mut acc, (i, v)| {
+                    if i > 2 {
+                        return Err("trailing content".into());
+                    }
+                    acc[i] = v?;
+                    Ok(acc)
+                })
         })
+        .collect::<Result<_, Box<dyn Error>>>()
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut input = vec![];
+    stdin().lock().read_to_end(&mut input)?;
+    let cubes = cubes(&input)?;
+
+    let grid = Grid::new(&cubes)?;
+    let Some((outside, pockets)) = grid.air() else {
+        println!("0");
+        return Ok(());
+    };
+    println!("{}", outside.faces.len());
+    println!();
+
+    let inside = pockets
+        .iter()
+        .map(|pocket| pocket.faces.len())
         .sum::<usize>();
-    println!("{}", result);
+    println!("{} air pockets, {} faces inside", pockets.len(), inside);
+    for pocket in pockets.iter().take(LIST_LIMIT) {
+        println!(
+            "\t{} cells between {:?} and {:?}, {} faces",
+            pocket.volume,
+            pocket.min,
+            pocket.max,
+            pocket.faces.len()
+        );
+    }
+    if pockets.len() > LIST_LIMIT {
+        println!("\t... and {} more", pockets.len() - LIST_LIMIT);
+    }
+
+    let surface = grid.surface();
+    println!("{} faces in total", surface);
+    if surface != outside.faces.len() + inside {
+        return Err("faces inside and outside don't add up to the total".into());
+    }
+
+    if let Some(path) = args().nth(1) {
+        fs::write(&path, mesh(&path, &outside.faces)?)?;
+    }
 
     Ok(())
 }
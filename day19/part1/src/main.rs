@@ -1,26 +1,40 @@
-use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashSet};
+use std::collections::BTreeMap;
+use std::env::args;
 use std::fmt::Debug;
-use std::hash::{Hash, Hasher};
+use std::fs;
 use std::io::{stdin, Read};
-use std::iter::once;
 use std::num::ParseIntError;
 use std::ops::{Deref, DerefMut, Index, IndexMut};
-use std::thread;
-use std::thread::available_parallelism;
-use std::time::{Duration, Instant};
 
-use crossbeam::channel::{bounded, unbounded};
-use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::character::complete::{digit1, multispace0, multispace1};
-use nom::combinator::{all_consuming, map_res};
-use nom::error::{ContextError, ErrorKind, FromExternalError, ParseError, VerboseError};
+use nom::character::complete::{alpha1, digit1, multispace0, multispace1};
+use nom::combinator::{all_consuming, map, map_res};
+use nom::error::{ContextError, FromExternalError, ParseError, VerboseError};
 use nom::multi::separated_list1;
 use nom::sequence::tuple;
 use nom::{Finish, IResult};
+use rayon::prelude::*;
+use serde::Serialize;
+
+#[allow(dead_code)]
+enum Selection {
+    // every blueprint of the input
+    All,
+    // the first blueprints of the input
+    First(usize),
+    // the blueprints with these ids
+    Ids(&'static [usize]),
+}
 
-const MAX_TIME: usize = 25;
+const BLUEPRINTS: Selection = Selection::All;
+// every selected blueprint gets evaluated for each of these
+const HORIZONS: &[usize] = &[24];
+// material to get as much as possible of
+const TARGET: &str = "geode";
+// the one robot there is at the start
+const START: &str = "ore";
+// most material types a blueprint can use
+const MAX_MATERIALS: usize = 8;
 
 trait ExpressionParseError<'a>:
     ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, ParseIntError> + Debug
@@ -33,32 +47,18 @@ fn number<'a, E: ExpressionParseError<'a>>(i: &'a str) -> IResult<&'a str, usize
     map_res(digit1, |s: &str| s.parse())(i)
 }
 
-#[derive(Debug, Hash)]
-#[repr(usize)]
-enum Material {
-    Ore = 0,
-    Clay,
-    Obsidian,
-    Geode,
-}
+type Amounts = Materials<MAX_MATERIALS>;
 
+// what each robot costs, indexed like the chain
 #[derive(Debug, Copy, Clone, Hash)]
-struct BluePrint([Materials<4>; 4]);
-
-impl Index<Material> for BluePrint {
-    type Output = Materials<4>;
-
-    fn index(&self, index: Material) -> &Self::Output {
-        &self.0[index as usize]
-    }
-}
+struct BluePrint([Amounts; MAX_MATERIALS]);
 
 #[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
 struct Materials<const N: usize>([usize; N]);
 
-impl Default for Materials<4> {
+impl<const N: usize> Default for Materials<N> {
     fn default() -> Self {
-        Self([0, 0, 0, 0])
+        Self([0; N])
     }
 }
 
@@ -76,14 +76,6 @@ impl<const N: usize> DerefMut for Materials<N> {
     }
 }
 
-impl<const N: usize> Index<Material> for Materials<N> {
-    type Output = usize;
-
-    fn index(&self, index: Material) -> &Self::Output {
-        &self[index as usize]
-    }
-}
-
 impl<const N: usize> Index<usize> for Materials<N> {
     type Output = usize;
 
@@ -98,196 +90,402 @@ impl<const N: usize> IndexMut<usize> for Materials<N> {
     }
 }
 
-impl<const N: usize> IndexMut<Material> for Materials<N> {
-    fn index_mut(&mut self, index: Material) -> &mut Self::Output {
-        &mut self[index as usize]
-    }
-}
+// robot and what it costs, as written in the input
+type RawRobot<'a> = (&'a str, Vec<(usize, &'a str)>);
+type RawBluePrint<'a> = (usize, Vec<RawRobot<'a>>);
 
-fn materials<'a, E: ExpressionParseError<'a>>(i: &'a str) -> IResult<&'a str, Materials<4>, E> {
-    let (rest, list) = separated_list1(
+fn costs<'a, E: ExpressionParseError<'a>>(
+    i: &'a str,
+) -> IResult<&'a str, Vec<(usize, &'a str)>, E> {
+    separated_list1(
         tag(" and "),
-        tuple((
-            number::<E>,
-            multispace1,
-            alt((tag("ore"), tag("clay"), tag("obsidian"))),
-        )),
-    )(i)?;
-
-    list.into_iter()
-        .try_fold(
-            Materials::default(),
-            |mut materials, (amount, _, material)| {
-                match material {
-                    "ore" if materials[Material::Ore] > 0 => Err("ore was set twice")?,
-                    "ore" => {
-                        materials[Material::Ore] = amount;
-                    }
-                    "clay" if materials[Material::Clay] > 0 => Err("clay was set twice")?,
-                    "clay" => {
-                        materials[Material::Clay] = amount;
-                    }
-                    "obsidian" if materials[Material::Obsidian] > 0 => {
-                        Err("obsidian was set twice")?
-                    }
-                    "obsidian" => {
-                        materials[Material::Obsidian] = amount;
-                    }
-                    _ => unreachable!(),
-                };
-                Ok::<_, &str>(materials)
-            },
-        )
-        .map_err(|_| nom::Err::Failure(E::from_error_kind(rest, ErrorKind::Tag)))
-        .map(|materials| (rest, materials))
+        map(
+            tuple((number::<E>, multispace1, alpha1)),
+            |(amount, _, material)| (amount, material),
+        ),
+    )(i)
+}
+
+fn robot<'a, E: ExpressionParseError<'a>>(i: &'a str) -> IResult<&'a str, RawRobot<'a>, E> {
+    map(
+        tuple((tag("Each "), alpha1, tag(" robot costs "), costs, tag("."))),
+        |(_, robot, _, costs, _)| (robot, costs),
+    )(i)
 }
 
-fn blueprints<'a, E: ExpressionParseError<'a>>(i: &'a str) -> IResult<&'a str, Vec<BluePrint>, E> {
-    let (rest, raw_blueprints) = separated_list1(
+fn blueprints<'a, E: ExpressionParseError<'a>>(
+    i: &'a str,
+) -> IResult<&'a str, Vec<RawBluePrint<'a>>, E> {
+    separated_list1(
         multispace1,
-        tuple((
-            tag("Blueprint "),
-            number,
-            tag(":"),
-            multispace1,
-            tag("Each ore robot costs "),
-            materials,
-            tag("."),
-            multispace1,
-            tag("Each clay robot costs "),
-            materials,
-            tag("."),
-            multispace1,
-            tag("Each obsidian robot costs "),
-            materials,
-            tag("."),
-            multispace1,
-            tag("Each geode robot costs "),
-            materials,
-            tag("."),
-        )),
-    )(i)?;
-
-    Ok((
-        rest,
-        Vec::from_iter(raw_blueprints.into_iter().map(
-            |(_, _, _, _, _, ore, _, _, _, clay, _, _, _, obsidian, _, _, _, geode, _)| {
-                BluePrint([ore, clay, obsidian, geode])
-            },
-        )),
-    ))
+        map(
+            tuple((
+                tag("Blueprint "),
+                number,
+                tag(":"),
+                multispace1,
+                separated_list1(multispace1, robot),
+            )),
+            |(_, id, _, _, robots)| (id, robots),
+        ),
+    )(i)
+}
+
+// the materials in the order their robots first show up in the input
+struct Chain {
+    names: Vec<String>,
+    target: usize,
+    start: usize,
+}
+
+impl Chain {
+    fn new(blueprints: &[RawBluePrint], target: &str, start: &str) -> Result<Self, String> {
+        let mut names: Vec<String> = vec![];
+        for (robot, _) in blueprints.iter().flat_map(|(_, robots)| robots) {
+            if !names.iter().any(|name| name == robot) {
+                names.push(robot.to_string());
+            }
+        }
+        if names.len() > MAX_MATERIALS {
+            return Err(format!("at most {} materials are supported", MAX_MATERIALS));
+        }
+
+        let find = |material: &str| {
+            names
+                .iter()
+                .position(|name| name == material)
+                .ok_or_else(|| format!("no {} robot in the blueprints", material))
+        };
+        Ok(Self {
+            target: find(target)?,
+            start: find(start)?,
+            names,
+        })
+    }
+
+    fn material(&self, id: usize, name: &str) -> Result<usize, String> {
+        self.names
+            .iter()
+            .position(|other| other == name)
+            .ok_or_else(|| format!("blueprint {}: no robot collects {}", id, name))
+    }
+
+    fn blueprint(&self, (id, robots): &RawBluePrint) -> Result<(usize, BluePrint), String> {
+        let mut blueprint = BluePrint([Default::default(); MAX_MATERIALS]);
+        let mut listed = [false; MAX_MATERIALS];
+        for (robot, costs) in robots {
+            let robot = self.material(*id, robot)?;
+            if listed[robot] {
+                return Err(format!(
+                    "blueprint {}: {} robot was listed twice",
+                    id, self.names[robot]
+                ));
+            }
+            listed[robot] = true;
+
+            for (amount, material) in costs {
+                let material = self.material(*id, material)?;
+                if blueprint.0[robot][material] > 0 {
+                    return Err(format!(
+                        "blueprint {}: {} was set twice",
+                        id, self.names[material]
+                    ));
+                }
+                blueprint.0[robot][material] = *amount;
+            }
+        }
+
+        match listed[..self.names.len()].iter().position(|listed| !listed) {
+            Some(missing) => Err(format!(
+                "blueprint {}: no {} robot",
+                id, self.names[missing]
+            )),
+            None => Ok((*id, blueprint)),
+        }
+    }
 }
 
+// robot that got built and the state once it is ready
+type Build = (usize, State);
+
 #[derive(Debug, Clone, Copy)]
 struct State {
-    blueprint: BluePrint,
-    materials: Materials<4>,
-    robots: Materials<4>,
+    materials: Amounts,
+    robots: Amounts,
     time: usize,
 }
 
-impl Eq for State {}
+impl State {
+    fn new(start: usize) -> Self {
+        let mut robots = Amounts::default();
+        robots[start] = 1;
+        Self {
+            materials: Default::default(),
+            robots,
+            time: 0,
+        }
+    }
+
+    // target material there is at the end if no more robots get built
+    fn collected(&self, target: usize, minutes: usize) -> usize {
+        self.materials[target] + self.robots[target] * (minutes - self.time)
+    }
+
+    // state right after the robot is built, waiting for the materials it needs first
+    fn build(&self, blueprint: &BluePrint, robot: usize) -> Option<Self> {
+        let costs = blueprint.0[robot];
+        let wait = costs
+            .iter()
+            .enumerate()
+            .try_fold(0, |wait: usize, (material, &cost)| {
+                let missing = cost.saturating_sub(self.materials[material]);
+                match (missing, self.robots[material]) {
+                    (0, _) => Some(wait),
+                    (_, 0) => None,
+                    (missing, robots) => Some(wait.max(missing.div_ceil(robots))),
+                }
+            })?;
+
+        let mut next = *self;
+        for material in 0..MAX_MATERIALS {
+            next.materials[material] += self.robots[material] * (wait + 1);
+            next.materials[material] -= costs[material];
+        }
+        next.robots[robot] += 1;
+        next.time += wait + 1;
+        Some(next)
+    }
+
+    // `path` holds the robots built to get to this state, `best` the most found and how
+    fn explore(
+        &self,
+        blueprint: &BluePrint,
+        chain: &Chain,
+        caps: &Amounts,
+        minutes: usize,
+        path: &mut Vec<Build>,
+        best: &mut (usize, Vec<Build>),
+    ) {
+        let collected = self.collected(chain.target, minutes);
+        if collected > best.0 {
+            *best = (collected, path.clone());
+        }
 
-impl PartialEq<Self> for State {
-    fn eq(&self, other: &Self) -> bool {
-        self.cmp(other) == Ordering::Equal
+        // even a new target robot every remaining minute couldn't do better
+        let remaining = minutes - self.time;
+        if collected + remaining * remaining.saturating_sub(1) / 2 <= best.0 {
+            return;
+        }
+
+        for robot in (0..chain.names.len()).rev() {
+            // more of a material than can be spent until the end is useless
+            if robot != chain.target
+                && self.robots[robot] * remaining + self.materials[robot] >= caps[robot] * remaining
+            {
+                continue;
+            }
+            match self.build(blueprint, robot) {
+                Some(next) if next.time < minutes => {
+                    path.push((robot, next));
+                    next.explore(blueprint, chain, caps, minutes, path, best);
+                    path.pop();
+                }
+                _ => {}
+            }
+        }
     }
 }
 
-impl Hash for State {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.blueprint.hash(state);
-        self.robots.hash(state);
-        self.materials.hash(state);
-        self.time.hash(state);
+impl BluePrint {
+    // most of each material a robot costs, which is the most that can be spent in a minute
+    fn caps(&self) -> Amounts {
+        let mut caps = Amounts::default();
+        for costs in self.0 {
+            for material in 0..MAX_MATERIALS {
+                caps[material] = caps[material].max(costs[material]);
+            }
+        }
+        caps
+    }
+
+    // most of the target collected within the time, and the robots to build for it
+    fn best(&self, chain: &Chain, minutes: usize) -> (usize, Vec<Build>) {
+        let mut best = (0, vec![]);
+        State::new(chain.start).explore(self, chain, &self.caps(), minutes, &mut vec![], &mut best);
+        best
     }
 }
 
-impl PartialOrd<Self> for State {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl Selection {
+    fn select(
+        &self,
+        blueprints: Vec<(usize, BluePrint)>,
+    ) -> Result<Vec<(usize, BluePrint)>, String> {
+        match self {
+            Selection::All => Ok(blueprints),
+            Selection::First(count) => Ok(blueprints.into_iter().take(*count).collect()),
+            Selection::Ids(ids) => ids
+                .iter()
+                .map(|id| {
+                    blueprints
+                        .iter()
+                        .find(|(other, _)| other == id)
+                        .copied()
+                        .ok_or_else(|| format!("no blueprint {}", id))
+                })
+                .collect(),
+        }
     }
 }
 
-impl Ord for State {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let key = |state: &State| {
-            (
-                state.time,
-                state.robots[Material::Geode],
-                state.materials[Material::Geode],
-                state.materials[Material::Obsidian],
-                state.robots[Material::Obsidian],
-                state.materials[Material::Clay],
-                state.robots[Material::Clay],
-                state.materials[Material::Ore],
-                state.robots[Material::Ore],
-            )
-        };
+// most of the target a blueprint collects within a time horizon
+struct Optimum {
+    id: usize,
+    blueprint: BluePrint,
+    minutes: usize,
+    amount: usize,
+    builds: Vec<Build>,
+}
 
-        key(self).cmp(&key(other))
+// geodes can be counted, the other materials of the puzzle can't
+fn quantity(amount: usize, name: &str) -> String {
+    match (name, amount) {
+        ("geode", amount) if amount != 1 => format!("{} geodes", amount),
+        (name, amount) => format!("{} {}", amount, name),
     }
 }
 
-impl State {
-    fn new(blueprint: BluePrint) -> Self {
-        Self {
-            blueprint,
-            materials: Default::default(),
-            robots: Materials([1, 0, 0, 0]),
-            time: 0,
-        }
+fn robot_name(name: &str) -> String {
+    match name {
+        "geode" => "geode-cracking robot".to_string(),
+        name => format!("{}-collecting robot", name),
     }
+}
+
+// prints every minute of the builds the way the puzzle describes them
+fn walkthrough(chain: &Chain, blueprint: &BluePrint, builds: &[Build], minutes: usize) {
+    let mut state = State::new(chain.start);
+    let mut builds = builds.iter().peekable();
+    for minute in 1..=minutes {
+        println!("== Minute {} ==", minute);
+
+        let building = builds.next_if(|(_, built)| built.time == minute);
+        if let Some((robot, _)) = building {
+            let costs = blueprint.0[*robot];
+            let spent = chain
+                .names
+                .iter()
+                .enumerate()
+                .filter(|(material, _)| costs[*material] > 0)
+                .map(|(material, name)| quantity(costs[material], name))
+                .collect::<Vec<_>>()
+                .join(" and ");
+            let name = robot_name(&chain.names[*robot]);
+            let article = if name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+                "an"
+            } else {
+                "a"
+            };
+            println!("Spend {} to start building {} {}.", spent, article, name);
+            for material in 0..MAX_MATERIALS {
+                state.materials[material] -= costs[material];
+            }
+        }
+
+        for (material, name) in chain.names.iter().enumerate() {
+            let robots = state.robots[material];
+            if robots == 0 {
+                continue;
+            }
+            state.materials[material] += robots;
+            let total = state.materials[material];
+            let (plural, verb) = if robots == 1 { ("", "s") } else { ("s", "") };
+            if name == "geode" {
+                println!(
+                    "{} {}{} crack{} {}; you now have {} open {}.",
+                    robots,
+                    robot_name(name),
+                    plural,
+                    verb,
+                    quantity(robots, name),
+                    total,
+                    if total == 1 { "geode" } else { "geodes" }
+                );
+            } else {
+                println!(
+                    "{} {}{} collect{} {}; you now have {}.",
+                    robots,
+                    robot_name(name),
+                    plural,
+                    verb,
+                    quantity(robots, name),
+                    quantity(total, name)
+                );
+            }
+        }
 
-    fn next(&self) -> impl Iterator<Item = State> + '_ {
-        (self.time + 1 < MAX_TIME)
-            .then_some(
-                self.blueprint
-                    .0
-                    .iter()
-                    .enumerate()
-                    .flat_map(|(robot_type, robot_materials)| {
-                        robot_materials
-                            .into_iter()
-                            .enumerate()
-                            .try_fold(
-                                (robot_type, self.materials),
-                                |(robot_type, mut remaining), (material_type, cost)| {
-                                    remaining[material_type] = remaining[material_type]
-                                        .checked_sub(cost)
-                                        .ok_or("insufficient materials")?;
-                                    Ok::<_, &'static str>((robot_type, remaining))
-                                },
-                            )
-                            .map(|(robot_type, remaining)| (Some(robot_type), remaining))
-                    })
-                    .chain(once((None, self.materials)))
-                    .map(move |(robot_type, mut materials)| {
-                        for (material_type, remaining) in materials.into_iter().enumerate() {
-                            materials[material_type] = remaining + self.robots[material_type]
-                        }
-
-                        let robots = robot_type
-                            .map(|robot_type| {
-                                let mut robots = self.robots;
-                                robots[robot_type] += 1;
-                                robots
-                            })
-                            .unwrap_or(self.robots);
-
-                        State {
-                            blueprint: self.blueprint,
-                            materials,
-                            robots,
-                            time: self.time + 1,
-                        }
-                    }),
-            )
-            .into_iter()
-            .flatten()
+        if let Some((robot, _)) = building {
+            state.robots[*robot] += 1;
+            println!(
+                "The new {} is ready; you now have {} of them.",
+                robot_name(&chain.names[*robot]),
+                state.robots[*robot]
+            );
+        }
+        println!();
     }
 }
 
+// the materials by name
+fn inventory<'a>(chain: &'a Chain, materials: &Amounts) -> BTreeMap<&'a str, usize> {
+    chain
+        .names
+        .iter()
+        .map(String::as_str)
+        .zip(materials.iter().copied())
+        .collect()
+}
+
+#[derive(Serialize)]
+struct BuildStep<'a> {
+    minute: usize,
+    robot: &'a str,
+    materials: BTreeMap<&'a str, usize>,
+    robots: BTreeMap<&'a str, usize>,
+}
+
+#[derive(Serialize)]
+struct Schedule<'a> {
+    blueprint: usize,
+    minutes: usize,
+    target: &'a str,
+    amount: usize,
+    builds: Vec<BuildStep<'a>>,
+}
+
+fn json(chain: &Chain, optima: &[Optimum]) -> Result<String, String> {
+    let schedules = optima
+        .iter()
+        .map(|optimum| Schedule {
+            blueprint: optimum.id,
+            minutes: optimum.minutes,
+            target: &chain.names[chain.target],
+            amount: optimum.amount,
+            builds: optimum
+                .builds
+                .iter()
+                .map(|(robot, state)| BuildStep {
+                    minute: state.time,
+                    robot: &chain.names[*robot],
+                    materials: inventory(chain, &state.materials),
+                    robots: inventory(chain, &state.robots),
+                })
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_string_pretty(&schedules).map_err(|err| err.to_string())
+}
+
 fn main() -> Result<(), String> {
     let mut output = String::new();
 
@@ -311,131 +509,76 @@ fn main() -> Result<(), String> {
         }
     };
 
-    let parallelism = available_parallelism().unwrap().get();
-    let (task_sender, task_receiver) = bounded::<(State, usize)>(parallelism);
-    let (result_sender, result_receiver) = unbounded::<(State, usize, bool)>();
-    let (working_sender, working_receiver) = unbounded::<i8>();
-
-    for _ in 0..parallelism {
-        let task_receiver = task_receiver.clone();
-        let result_sender = result_sender.clone();
-        let working_sender = working_sender.clone();
-        thread::spawn(move || {
-            while let Ok((state, blueprint)) = task_receiver.recv() {
-                working_sender.send(1).expect("working_sender error");
-
-                let generations = state
-                    .blueprint
-                    .0
-                    .into_iter()
-                    .enumerate()
-                    .fold([0; 4], |acc, (_, materials)| {
-                        [
-                            acc[0].max(materials[0]),
-                            acc[1].max(materials[1]),
-                            acc[2].max(materials[2]),
-                            acc[3].max(materials[3]),
-                        ]
-                    })
-                    .into_iter()
-                    .enumerate()
-                    .map(|(material, required)| required.saturating_sub(state.materials[material]))
-                    .max()
-                    .unwrap_or_default()
-                    .min(1);
-
-                let mut queue = state.next().map(|state| (state, 0)).collect::<Vec<_>>();
-
-                while let Some((state, generation)) = queue.pop() {
-                    if generation < generations {
-                        result_sender
-                            .send((state, blueprint, false))
-                            .expect("result_sender error");
-                        queue.extend(state.next().map(|state| (state, generation + 1)))
-                    } else {
-                        result_sender
-                            .send((state, blueprint, true))
-                            .expect("result_sender error");
-                    }
-                }
-                working_sender.send(-1).expect("working_sender error");
-            }
-        });
-    }
-
-    let mut max = Vec::from_iter((0..blueprints.len()).map(|_| 0));
-    let mut best_at = Vec::from_iter((0..blueprints.len()).map(|_| [0; MAX_TIME]));
-    let mut heap = BinaryHeap::<(State, usize)>::new();
+    let chain = Chain::new(&blueprints, TARGET, START)?;
+    let blueprints = blueprints
+        .iter()
+        .map(|blueprint| chain.blueprint(blueprint))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    for (n, blueprint) in blueprints.into_iter().enumerate() {
-        let state = State::new(blueprint);
-        heap.push((state, n));
+    let blueprints = BLUEPRINTS.select(blueprints)?;
+    if blueprints.is_empty() {
+        return Err("no blueprint selected".into());
     }
 
-    let mut done = HashSet::new();
-    let mut workers = 0;
-    let mut instant = Instant::now();
-    let max = loop {
-        let mut blocked_send = false;
-
-        while let Some((state, blueprint)) = heap.pop() {
-            if Instant::now() > instant + Duration::from_secs(5) {
-                println!("{} {:?}", blueprint, state.materials);
-                instant = Instant::now()
-            }
-            match task_sender.try_send((state, blueprint)) {
-                Ok(_) => {
-                    workers += working_receiver.recv().expect("working_receiver fail");
-                    while let Ok(working) = working_receiver.try_recv() {
-                        workers += working;
-                    }
-                }
-                Err(_) => {
-                    if blocked_send {
-                        break;
-                    }
-                    blocked_send = true;
-                }
-            }
-        }
-
-        let mut received = false;
-        while let Ok((state, blueprint, process)) = result_receiver.try_recv() {
-            received = true;
-
-            match best_at[blueprint][state.time].cmp(&state.robots[Material::Geode]) {
-                Ordering::Less => best_at[blueprint][state.time] = state.robots[Material::Geode],
-                Ordering::Equal => (),
-                Ordering::Greater => continue,
-            }
-
-            let candidate = state.materials[Material::Geode];
-            if candidate > max[blueprint] {
-                println!("{} {}", blueprint, candidate);
-                max[blueprint] = candidate;
+    let optima = HORIZONS
+        .iter()
+        .flat_map(|&minutes| {
+            blueprints
+                .iter()
+                .map(move |&(id, blueprint)| (id, blueprint, minutes))
+        })
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|(id, blueprint, minutes)| {
+            let (amount, builds) = blueprint.best(&chain, minutes);
+            Optimum {
+                id,
+                blueprint,
+                minutes,
+                amount,
+                builds,
             }
+        })
+        .collect::<Vec<_>>();
+
+    for (minutes, optima) in HORIZONS.iter().zip(optima.chunks(blueprints.len())) {
+        let max = optima
+            .iter()
+            .map(|optimum| optimum.amount)
+            .collect::<Vec<_>>();
+        let quality = optima
+            .iter()
+            .map(|optimum| optimum.id * optimum.amount)
+            .sum::<usize>();
+        // None once it doesn't fit anymore
+        let product = max
+            .iter()
+            .try_fold(1usize, |product, x| product.checked_mul(*x));
+        println!(
+            "{} minutes: {:?} quality level {} product {}",
+            minutes,
+            max,
+            quality,
+            product.map_or("too large".to_string(), |product| product.to_string())
+        );
+    }
 
-            if process && !done.contains(&(state, blueprint)) {
-                heap.push((state, blueprint));
-                done.insert((state, blueprint));
-            }
-        }
-
-        while let Ok(working) = working_receiver.try_recv() {
-            workers += working;
-        }
-
-        if heap.is_empty() && !blocked_send && !received && workers == 0 {
-            break max;
-        }
-    };
+    let target = &chain.names[chain.target];
+    for optimum in &optima {
+        println!();
+        println!(
+            "Blueprint {} in {} minutes: {}",
+            optimum.id,
+            optimum.minutes,
+            quantity(optimum.amount, target)
+        );
+        println!();
+        walkthrough(&chain, &optimum.blueprint, &optimum.builds, optimum.minutes);
+    }
 
-    let result = max
-        .into_iter()
-        .enumerate()
-        .map(|(n, max)| (n + 1) * max)
-        .sum::<usize>();
-    println!("{}", result);
+    if let Some(path) = args().nth(1) {
+        fs::write(path, json(&chain, &optima)?).map_err(|err| err.to_string())?;
+    }
 
     Ok(())
 }
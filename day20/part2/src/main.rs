@@ -3,59 +3,326 @@ use std::{
     io::{stdin, BufRead},
 };
 
-const FACTOR: i64 = 811589153;
+// every number gets multiplied by it before mixing
+const KEY: i64 = 811589153;
+const ROUNDS: usize = 10;
+// positions after the zero that get summed
+const OFFSETS: &[usize] = &[1000, 2000, 3000];
+// print the whole list before mixing and after each round
+const DUMP: bool = false;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let mut input = {
-        let lines = stdin().lock().lines();
-        lines
-            .enumerate()
-            .map(|(i, s)| Ok((s?.parse::<i64>()? * FACTOR, i)))
-            .collect::<Result<Vec<(i64, usize)>, Box<dyn Error>>>()?
-    };
+// links are indices into the nodes, packed so that more nodes share a cache line
+const NONE: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    priority: u32,
+    size: u32,
+    left: u32,
+    right: u32,
+    parent: u32,
+}
+
+fn link(node: Option<usize>) -> u32 {
+    node.map_or(NONE, |node| node as u32)
+}
+
+fn linked(link: u32) -> Option<usize> {
+    (link != NONE).then_some(link as usize)
+}
+
+// implicit treap over the original indices, its in-order traversal is the mixed list
+struct Treap {
+    nodes: Vec<Node>,
+    root: Option<usize>,
+    seed: u64,
+}
+
+impl Treap {
+    fn new(len: usize) -> Self {
+        let mut treap = Self {
+            nodes: Vec::with_capacity(len),
+            root: None,
+            seed: 0x2545f4914f6cdd1d,
+        };
+        for node in 0..len {
+            let priority = treap.random();
+            treap.nodes.push(Node {
+                priority,
+                size: 1,
+                left: NONE,
+                right: NONE,
+                parent: NONE,
+            });
+            treap.root = treap.merge(treap.root, Some(node));
+        }
+        treap.detach(treap.root);
+        treap
+    }
+
+    // xorshift, good enough to keep the tree balanced
+    fn random(&mut self) -> u32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        (self.seed >> 32) as u32
+    }
+
+    fn size(&self, node: Option<usize>) -> usize {
+        node.map_or(0, |node| self.nodes[node].size as usize)
+    }
+
+    fn left(&self, node: usize) -> Option<usize> {
+        linked(self.nodes[node].left)
+    }
+
+    fn right(&self, node: usize) -> Option<usize> {
+        linked(self.nodes[node].right)
+    }
+
+    fn parent(&self, node: usize) -> Option<usize> {
+        linked(self.nodes[node].parent)
+    }
+
+    fn detach(&mut self, node: Option<usize>) {
+        if let Some(node) = node {
+            self.nodes[node].parent = NONE;
+        }
+    }
+
+    fn update(&mut self, node: usize) {
+        self.nodes[node].size =
+            (1 + self.size(self.left(node)) + self.size(self.right(node))) as u32;
+    }
+
+    fn set_left(&mut self, node: usize, child: Option<usize>) {
+        self.nodes[node].left = link(child);
+        if let Some(child) = child {
+            self.nodes[child].parent = node as u32;
+        }
+    }
+
+    fn set_right(&mut self, node: usize, child: Option<usize>) {
+        self.nodes[node].right = link(child);
+        if let Some(child) = child {
+            self.nodes[child].parent = node as u32;
+        }
+    }
 
-    let mut destinations = input.clone();
+    fn merge(&mut self, left: Option<usize>, right: Option<usize>) -> Option<usize> {
+        match (left, right) {
+            (None, node) | (node, None) => node,
+            (Some(left), Some(right)) => {
+                if self.nodes[left].priority > self.nodes[right].priority {
+                    let merged = self.merge(self.right(left), Some(right));
+                    self.set_right(left, merged);
+                    self.update(left);
+                    Some(left)
+                } else {
+                    let merged = self.merge(Some(left), self.left(right));
+                    self.set_left(right, merged);
+                    self.update(right);
+                    Some(right)
+                }
+            }
+        }
+    }
 
-    for n in 0..input.len() * 10 {
-        let n = n % input.len();
-        let (mut delta, old_pos) = input[n];
+    // the first `count` nodes and the rest
+    fn split(&mut self, node: Option<usize>, count: usize) -> (Option<usize>, Option<usize>) {
+        let Some(node) = node else {
+            return (None, None);
+        };
+        let left = self.left(node);
+        if self.size(left) >= count {
+            let (first, rest) = self.split(left, count);
+            self.set_left(node, rest);
+            self.update(node);
+            self.detach(first);
+            (first, Some(node))
+        } else {
+            let right = self.right(node);
+            let (first, rest) = self.split(right, count - self.size(left) - 1);
+            self.set_right(node, first);
+            self.update(node);
+            self.detach(rest);
+            (Some(node), rest)
+        }
+    }
+
+    // index of the node in the list
+    fn position(&self, mut node: usize) -> usize {
+        let mut position = self.size(self.left(node));
+        while let Some(parent) = self.parent(node) {
+            // the parent and its left subtree come first, sized without visiting that subtree
+            if self.right(parent) == Some(node) {
+                position += (self.nodes[parent].size - self.nodes[node].size) as usize;
+            }
+            node = parent;
+        }
+        position
+    }
 
-        delta %= input.len() as i64 - 1;
-        if delta < 0 {
-            delta += (input.len() as i64) - 1
+    // takes the node out, its children take its place
+    fn remove(&mut self, node: usize) {
+        let merged = self.merge(self.left(node), self.right(node));
+        match self.parent(node) {
+            Some(parent) => {
+                if self.left(parent) == Some(node) {
+                    self.set_left(parent, merged);
+                } else {
+                    self.set_right(parent, merged);
+                }
+                let mut ancestor = Some(parent);
+                while let Some(current) = ancestor {
+                    self.nodes[current].size -= 1;
+                    ancestor = self.parent(current);
+                }
+            }
+            None => {
+                self.root = merged;
+                self.detach(merged);
+            }
         }
+    }
 
-        let new_pos = (old_pos + delta as usize) % input.len();
+    fn insert(&mut self, node: usize, position: usize) {
+        self.nodes[node] = Node {
+            size: 1,
+            left: NONE,
+            right: NONE,
+            parent: NONE,
+            ..self.nodes[node]
+        };
+        self.root = self.insert_into(self.root, node, position);
+        self.detach(self.root);
+    }
+
+    // goes down until the node has the higher priority and splits the rest around it
+    fn insert_into(&mut self, root: Option<usize>, node: usize, position: usize) -> Option<usize> {
+        match root {
+            Some(current) if self.nodes[current].priority > self.nodes[node].priority => {
+                let left = self.left(current);
+                let left_size = self.size(left);
+                if position <= left_size {
+                    let inserted = self.insert_into(left, node, position);
+                    self.set_left(current, inserted);
+                } else {
+                    let right = self.right(current);
+                    let inserted = self.insert_into(right, node, position - left_size - 1);
+                    self.set_right(current, inserted);
+                }
+                self.nodes[current].size += 1;
+                Some(current)
+            }
+            _ => {
+                let (first, rest) = self.split(root, position);
+                self.set_left(node, first);
+                self.set_right(node, rest);
+                self.update(node);
+                Some(node)
+            }
+        }
+    }
 
-        if old_pos == new_pos {
-            continue;
+    // original indices in list order
+    fn order(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack = vec![];
+        let mut node = self.root;
+        while node.is_some() || !stack.is_empty() {
+            while let Some(current) = node {
+                stack.push(current);
+                node = self.left(current);
+            }
+            if let Some(current) = stack.pop() {
+                order.push(current);
+                node = self.right(current);
+            }
         }
+        order
+    }
+}
 
-        let mut pull_index = old_pos;
-        loop {
-            let next_index = (pull_index + 1) % input.len();
-            let item_to_move = destinations[next_index];
+// the numbers in list order, starting from the zero
+fn dump(input: &[i64], order: &[usize], zero: usize) -> String {
+    let start = order.iter().position(|n| *n == zero).unwrap_or(0);
+    order[start..]
+        .iter()
+        .chain(&order[..start])
+        .map(|n| input[*n].to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let input = {
+        let lines = stdin().lock().lines();
+        lines
+            .map(|s| {
+                s?.parse::<i64>()?
+                    .checked_mul(KEY)
+                    .ok_or_else(|| "number too large once decrypted".into())
+            })
+            .collect::<Result<Vec<i64>, Box<dyn Error>>>()?
+    };
 
-            input[item_to_move.1].1 = pull_index;
+    let zero = {
+        let mut zeros = input
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| **n == 0)
+            .map(|(i, _)| i);
+        match (zeros.next(), zeros.next()) {
+            (Some(zero), None) => zero,
+            (None, _) => return Err("no zero in the list".into()),
+            (Some(_), Some(_)) => return Err("more than one zero in the list".into()),
+        }
+    };
 
-            destinations[pull_index] = item_to_move;
+    if input.len() >= NONE as usize {
+        return Err("too many numbers in the list".into());
+    }
 
-            if next_index == new_pos {
-                input[n].1 = new_pos;
+    let mut treap = Treap::new(input.len());
+    if DUMP {
+        println!("Initial arrangement:");
+        println!("{}", dump(&input, &treap.order(), zero));
+        println!();
+    }
 
-                destinations[next_index] = (input[n].0, n);
-                break;
+    // the other numbers, which the one moving goes around, a lone number stays where it is
+    let others = (input.len() as i64 - 1).max(1);
+    for round in 1..=ROUNDS {
+        for (n, delta) in input.iter().enumerate() {
+            let old_pos = treap.position(n);
+            // reduced first so that adding the position can't overflow
+            let new_pos = (old_pos as i64 + delta.rem_euclid(others)).rem_euclid(others) as usize;
+            if old_pos == new_pos {
+                continue;
             }
 
-            pull_index = (pull_index + 1) % input.len()
+            treap.remove(n);
+            treap.insert(n, new_pos);
+        }
+
+        if DUMP {
+            println!(
+                "After {} round{} of mixing:",
+                round,
+                if round == 1 { "" } else { "s" }
+            );
+            println!("{}", dump(&input, &treap.order(), zero));
+            println!();
         }
     }
 
-    let start = input.iter().find(|n| n.0 == 0).unwrap().1;
+    let destinations = treap.order();
+    let start = destinations.iter().position(|n| *n == zero).unwrap();
 
-    let result = [1000usize, 2000, 3000]
-        .into_iter()
-        .map(|delta| destinations[(delta + start) % input.len()].0)
+    let result = OFFSETS
+        .iter()
+        .map(|delta| input[destinations[(delta + start) % input.len()]])
         .sum::<i64>();
 
     println!("{}", result);
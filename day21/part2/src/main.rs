@@ -1,8 +1,8 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 
 use std::{
     error::Error,
-    fmt::Debug,
+    fmt::{self, Debug, Display, Formatter},
     io::{stdin, BufRead},
     num::ParseIntError,
 };
@@ -17,6 +17,9 @@ use nom::{
     Finish, IResult,
 };
 
+const HUMAN: &str = "humn";
+const OVERFLOW: &str = "number too large";
+
 trait ExpressionParseError<'a>:
     ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, ParseIntError> + Debug
 {
@@ -85,47 +88,179 @@ fn monkey<'a, E: ExpressionParseError<'a>>(i: &'a str) -> IResult<&'a str, Monke
     ))
 }
 
-fn solve(op: Operation, a: i64, b: i64) -> i64 {
-    match op {
-        Operation::Add => a + b,
-        Operation::Subtract => a - b,
-        Operation::Multiply => a * b,
-        Operation::Divide => a / b,
+// exact fraction, kept reduced with a positive denominator
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl Rational {
+    const ZERO: Self = Self {
+        numerator: 0,
+        denominator: 1,
+    };
+    const ONE: Self = Self {
+        numerator: 1,
+        denominator: 1,
+    };
+
+    // None when dividing by zero
+    fn new(numerator: i128, denominator: i128) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+        let divisor = gcd(numerator, denominator) * denominator.signum();
+        Some(Self {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        })
+    }
+
+    fn integer(&self) -> Option<i128> {
+        (self.denominator == 1).then_some(self.numerator)
+    }
+
+    // the operations give None if the result doesn't fit
+    fn add(self, other: Self) -> Option<Self> {
+        Self::new(
+            (self.numerator.checked_mul(other.denominator)?)
+                .checked_add(other.numerator.checked_mul(self.denominator)?)?,
+            self.denominator.checked_mul(other.denominator)?,
+        )
+    }
+
+    fn sub(self, other: Self) -> Option<Self> {
+        self.add(Self {
+            numerator: other.numerator.checked_neg()?,
+            ..other
+        })
+    }
+
+    fn mul(self, other: Self) -> Option<Self> {
+        Self::new(
+            self.numerator.checked_mul(other.numerator)?,
+            self.denominator.checked_mul(other.denominator)?,
+        )
+    }
+
+    fn div(self, other: Self) -> Option<Self> {
+        self.mul(Self::new(other.denominator, other.numerator)?)
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(integer: i64) -> Self {
+        Self {
+            numerator: integer as i128,
+            denominator: 1,
+        }
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.integer() {
+            Some(integer) => write!(f, "{}", integer),
+            None => write!(f, "{}/{}", self.numerator, self.denominator),
+        }
+    }
+}
+
+// `humn * x + constant`
+#[derive(Debug, Copy, Clone)]
+struct Linear {
+    x: Rational,
+    constant: Rational,
+}
+
+impl Linear {
+    fn constant(constant: Rational) -> Self {
+        Self {
+            x: Rational::ZERO,
+            constant,
+        }
     }
+
+    fn apply(op: Operation, a: Self, b: Self) -> Result<Self, &'static str> {
+        match op {
+            Operation::Add => Ok(Self {
+                x: a.x.add(b.x).ok_or(OVERFLOW)?,
+                constant: a.constant.add(b.constant).ok_or(OVERFLOW)?,
+            }),
+            Operation::Subtract => Ok(Self {
+                x: a.x.sub(b.x).ok_or(OVERFLOW)?,
+                constant: a.constant.sub(b.constant).ok_or(OVERFLOW)?,
+            }),
+            Operation::Multiply if a.x != Rational::ZERO && b.x != Rational::ZERO => {
+                Err("humn gets multiplied by itself, the equation isn't linear")
+            }
+            Operation::Multiply => {
+                // one of the two is a constant
+                let (factor, other) = if a.x == Rational::ZERO {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+                Ok(Self {
+                    x: other.x.mul(factor.constant).ok_or(OVERFLOW)?,
+                    constant: other.constant.mul(factor.constant).ok_or(OVERFLOW)?,
+                })
+            }
+            Operation::Divide if b.x != Rational::ZERO => {
+                Err("something gets divided by humn, the equation isn't linear")
+            }
+            Operation::Divide if b.constant == Rational::ZERO => Err("division by zero"),
+            Operation::Divide => Ok(Self {
+                x: a.x.div(b.constant).ok_or(OVERFLOW)?,
+                constant: a.constant.div(b.constant).ok_or(OVERFLOW)?,
+            }),
+        }
+    }
+}
+
+trait Solver {
+    fn solve(&self, name: &str, cache: &mut HashMap<String, Linear>) -> Result<Linear, String>;
 }
 
-fn reorderings(
-    a: String,
-    op: Operation,
-    b: String,
-    c: String,
-) -> [(String, (Operation, String, String)); 3] {
-    match op {
-        Operation::Add => [
-            (a.clone(), (Operation::Add, b.clone(), c.clone())),
-            (b.clone(), (Operation::Subtract, a.clone(), c.clone())),
-            (c, (Operation::Subtract, a, b)),
-        ],
-        Operation::Subtract => [
-            (a.clone(), (Operation::Subtract, b.clone(), c.clone())),
-            (b.clone(), (Operation::Add, a.clone(), c.clone())),
-            (c, (Operation::Subtract, b, a)),
-        ],
-        Operation::Multiply => [
-            (a.clone(), (Operation::Multiply, b.clone(), c.clone())),
-            (b.clone(), (Operation::Divide, a.clone(), c.clone())),
-            (c, (Operation::Divide, a, b)),
-        ],
-        Operation::Divide => [
-            (a.clone(), (Operation::Divide, b.clone(), c.clone())),
-            (b.clone(), (Operation::Multiply, a.clone(), c.clone())),
-            (c, (Operation::Divide, b, a)),
-        ],
+impl Solver for HashMap<String, Expression> {
+    fn solve(&self, name: &str, cache: &mut HashMap<String, Linear>) -> Result<Linear, String> {
+        if let Some(result) = cache.get(name) {
+            return Ok(*result);
+        }
+
+        // the human's own number doesn't matter, it's the unknown
+        if name == HUMAN {
+            return Ok(Linear {
+                x: Rational::ONE,
+                constant: Rational::ZERO,
+            });
+        }
+
+        let solved = match self.get(name) {
+            Some(Expression::Operation(op, a, b)) => {
+                let a = self.solve(a, cache)?;
+                let b = self.solve(b, cache)?;
+                Linear::apply(*op, a, b).map_err(|err| format!("{}: {}", name, err))?
+            }
+            Some(Expression::Number(n)) => Linear::constant((*n).into()),
+            None => return Err(format!("monkey {} not found", name)),
+        };
+        cache.insert(name.to_string(), solved);
+        Ok(solved)
     }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut monkeys = {
+    let monkeys = {
         let lines = stdin().lock().lines();
         lines
             .map(|line| {
@@ -139,53 +274,34 @@ fn main() -> Result<(), Box<dyn Error>> {
             .collect::<Result<HashMap<String, Expression>, Box<dyn Error>>>()?
     };
 
-    let root = monkeys.remove("root").ok_or("root monkey not found")?;
-
-    let Expression::Operation(_, root_a, root_b) = root else {
-        return Err("root monkey should be an operation".into())
+    let Some(Expression::Operation(_, root_a, root_b)) = monkeys.get("root") else {
+        return Err("root monkey should be an operation".into());
     };
+    if !monkeys.contains_key(HUMAN) {
+        return Err("human not found".into());
+    }
 
-    monkeys.remove("humn").ok_or("human not found")?;
-
-    let mut known = HashMap::new();
+    let mut cache = HashMap::new();
+    let a = monkeys.solve(root_a, &mut cache)?;
+    let b = monkeys.solve(root_b, &mut cache)?;
 
-    let mut expressions = monkeys
-        .into_iter()
-        .filter_map(|(a, expression)| match expression {
-            Expression::Operation(op, b, c) => {
-                let a = if a == root_a || a == root_b {
-                    "root".to_string()
-                } else {
-                    a
-                };
-                let b = if b == root_a || b == root_b {
-                    "root".to_string()
-                } else {
-                    b
-                };
-                let c = if c == root_a || c == root_b {
-                    "root".to_string()
-                } else {
-                    c
-                };
-
-                Some(reorderings(a, op, b, c))
-            }
-            Expression::Number(i) => {
-                known.insert(a, i);
-                None
-            }
-        })
-        .flatten()
-        .collect::<VecDeque<_>>();
+    // a.x * humn + a.constant = b.x * humn + b.constant
+    let x = a.x.sub(b.x).ok_or(OVERFLOW)?;
+    let constant = b.constant.sub(a.constant).ok_or(OVERFLOW)?;
+    if x == Rational::ZERO {
+        return Err(if constant == Rational::ZERO {
+            "humn cancels out, any value works".into()
+        } else {
+            "humn cancels out, no value works".into()
+        });
+    }
 
-    while let Some((a, (operation, b, c))) = expressions.pop_front() {
-        if let (Some(i), Some(j)) = (known.get(&b), known.get(&c)) {
-            known.insert(a, solve(operation, *i, *j));
-            continue;
-        };
-        expressions.push_back((a, (operation, b, c)))
+    let humn = constant.div(x).ok_or(OVERFLOW)?;
+    match humn.integer() {
+        Some(humn) => println!("{}", humn),
+        None => {
+            return Err(format!("humn would have to be {}, which isn't an integer", humn).into())
+        }
     }
-    println!("{}", known["humn"]);
     Ok(())
 }